@@ -0,0 +1,171 @@
+//! A standard command line interface for Sylphie-based bots.
+//!
+//! Bot binaries can use [`main`] as their entry point to support the following subcommands:
+//!
+//! * `run [--headless] [--no-input]` starts the bot. This is the default if no subcommand is
+//!   given.
+//! * `exec <command>...` starts the bot, runs each terminal command given, then shuts it down.
+//! * `admin [command]...` connects to the admin socket of a running instance of the bot and runs
+//!   each command given on it, or reads commands from standard input if none are given.
+//! * `migrate` initializes and migrates the database, then exits.
+//! * `db-info` prints the migration versions and KVS tables in the database, then exits.
+
+use crate::core::{SylphieCoreBuilder, SylphieEvents};
+use crate::database::info::database_info;
+use crate::errors::*;
+use crate::interface::{EofBehavior, InterfaceMode};
+use crate::module::Module;
+use static_events::prelude_async::*;
+use tokio::runtime::Handle;
+
+/// A subcommand parsed from the command line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CliCommand {
+    /// Starts the bot normally.
+    Run(InterfaceMode),
+    /// Starts the bot, runs the given terminal commands, then shuts it down.
+    Exec(Vec<String>),
+    /// Runs the given terminal commands on an instance of the bot that is already running.
+    Admin(Vec<String>),
+    /// Initializes and migrates the database, then exits.
+    Migrate,
+    /// Prints information about the database, then exits.
+    DbInfo,
+    /// Prints usage information.
+    Help,
+}
+impl CliCommand {
+    /// Parses a subcommand from a list of command line arguments, not including the name of
+    /// the executable.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<CliCommand> {
+        let mut args = args.into_iter();
+        let command = match args.next() {
+            Some(command) => command,
+            None => return Ok(CliCommand::Run(InterfaceMode::Terminal)),
+        };
+        let rest: Vec<String> = args.collect();
+        match command.as_str() {
+            "run" => {
+                let mut mode = InterfaceMode::Terminal;
+                for arg in &rest {
+                    mode = match arg.as_str() {
+                        "--headless" => InterfaceMode::Headless(EofBehavior::Continue),
+                        "--no-input" => InterfaceMode::HeadlessNoInput,
+                        _ => cmd_error!("Unknown option for 'run': {}", arg),
+                    };
+                }
+                Ok(CliCommand::Run(mode))
+            }
+            "exec" => {
+                if rest.is_empty() {
+                    cmd_error!("'exec' requires at least one command.");
+                }
+                Ok(CliCommand::Exec(rest))
+            }
+            "admin" => Ok(CliCommand::Admin(rest)),
+            "migrate" | "db-info" | "help" | "--help" | "-h" => {
+                if !rest.is_empty() {
+                    cmd_error!("'{}' does not take any arguments.", command);
+                }
+                Ok(match command.as_str() {
+                    "migrate" => CliCommand::Migrate,
+                    "db-info" => CliCommand::DbInfo,
+                    _ => CliCommand::Help,
+                })
+            }
+            _ => cmd_error!("Unknown subcommand: {}", command),
+        }
+    }
+}
+
+fn print_usage(bin_name: &str) {
+    eprintln!("Usage:");
+    eprintln!("    {} [run] [--headless | --no-input]", bin_name);
+    eprintln!("    {} exec <command>...", bin_name);
+    eprintln!("    {} admin [command]...", bin_name);
+    eprintln!("    {} migrate", bin_name);
+    eprintln!("    {} db-info", bin_name);
+    eprintln!();
+    eprintln!("Subcommands:");
+    eprintln!("    run      Starts the bot. This is the default.");
+    eprintln!("    exec     Starts the bot, runs the given commands, then shuts it down.");
+    eprintln!("    admin    Runs commands on a running instance of the bot.");
+    eprintln!("    migrate  Initializes and migrates the database, then exits.");
+    eprintln!("    db-info  Prints the migration versions and KVS tables in the database.");
+    eprintln!();
+    eprintln!("Options for 'run':");
+    eprintln!("    --headless  Reads commands from standard input without a terminal prompt.");
+    eprintln!("    --no-input  Does not read standard input at all.");
+}
+
+fn print_db_info(target: &Handler<impl Events>) -> Result<()> {
+    let info = Handle::current().block_on(database_info(target))?;
+    let db_name = |is_transient| if is_transient { "transient" } else { "persistent" };
+
+    println!("Migrations:");
+    for migration in &info.migrations {
+        println!(
+            "    {} = v{} ({})",
+            migration.migration_name, migration.current_version, db_name(migration.is_transient),
+        );
+    }
+    println!("KVS tables:");
+    for table in &info.kvs_tables {
+        println!(
+            "    {} -> {} (schema v{}, {})",
+            table.module_path, table.table_name, table.schema_version,
+            db_name(table.is_transient),
+        );
+    }
+    Ok(())
+}
+
+/// Runs a bot according to the given subcommand.
+pub fn run_command<R: Module>(core: SylphieCoreBuilder<R>, command: CliCommand) -> Result<()> {
+    match command {
+        CliCommand::Run(mode) => core.interface_mode(mode).build().start(),
+        CliCommand::Exec(commands) =>
+            core.interface_mode(InterfaceMode::HeadlessNoInput).build().exec(commands),
+        CliCommand::Admin(commands) => core.build().connect_admin(commands),
+        CliCommand::Migrate => {
+            core.build().run_maintenance(|_| Ok(()))?;
+            println!("Database migrations completed successfully.");
+            Ok(())
+        }
+        CliCommand::DbInfo =>
+            core.build().run_maintenance(|target: &Handler<SylphieEvents<R>>| {
+                print_db_info(target)
+            }),
+        CliCommand::Help => {
+            print_usage(&bin_name());
+            Ok(())
+        }
+    }
+}
+
+fn bin_name() -> String {
+    std::env::args().next().unwrap_or_else(|| "sylphie".to_string())
+}
+
+/// Parses the command line arguments of the current process and runs the bot accordingly.
+///
+/// This exits the process with a non-zero status if the arguments are invalid or the bot
+/// returns an error.
+pub fn main<R: Module>(core: SylphieCoreBuilder<R>) {
+    let command = match CliCommand::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            match e.error_kind() {
+                ErrorKind::CommandError(msg) => eprintln!("{}", msg),
+                _ => eprintln!("{}", e),
+            }
+            eprintln!();
+            print_usage(&bin_name());
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run_command(core, command) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
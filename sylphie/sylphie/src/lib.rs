@@ -4,18 +4,23 @@
 #[doc(inline)] pub use sylphie_core::core;
 #[doc(inline)] pub use sylphie_core::errors;
 #[doc(inline)] pub use sylphie_core::interface;
+#[doc(inline)] pub use sylphie_core::i18n;
 #[doc(inline)] pub use sylphie_core::timer;
+#[doc(inline)] pub use sylphie_core::watchdog;
 #[doc(inline)] pub use sylphie_core::module;
 
 /// A module containing the command system.
 pub mod commands {
-    #[doc(inline)] pub use sylphie_commands::{commands, ctx, manager};
+    #[doc(inline)] pub use sylphie_commands::{
+        args, commands, ctx, dispatch, health, locale, log_levels, manager, module_state,
+        permissions,
+    };
 }
 
 /// A module containing types used for storing data persistantly.
 pub mod database {
     #[doc(inline)] pub use sylphie_database::{
-        connection, config, kvs, migrations, serializable, singleton,
+        connection, config, info, jobs, kvs, migrations, serializable, singleton,
     };
 }
 
@@ -26,7 +31,7 @@ pub mod connections {
 
 /// A module containing various types useful for the construction of Sylphie bots.
 pub mod utils {
-    #[doc(inline)] pub use sylphie_utils::{cache, disambiguate, locks};
+    #[doc(inline)] pub use sylphie_utils::{cache, disambiguate, locks, time};
 
     /// Types used to specify particular contexts such as users, members or servers.
     pub mod scopes {
@@ -67,6 +72,9 @@ macro_rules! sylphie_root_module {
 
 pub use sylphie_core::core::SylphieCore;
 
+pub mod cli;
+pub mod testing;
+
 /// Reexports of various types for macros. Not public API.
 #[doc(hidden)]
 pub mod __macro_export {
@@ -92,6 +100,7 @@ pub mod __macro_export {
 pub mod prelude {
     pub use crate::derives::*;
     pub use crate::sylphie_root_module;
+    pub use sylphie_commands::scoped_event;
     pub use sylphie_commands::prelude::*;
     pub use sylphie_core::prelude::*;
     pub use sylphie_utils::scopes::{Scope, ScopeArgs};
@@ -102,6 +111,8 @@ pub mod prelude {
 pub mod derives {
     #[doc(inline)] pub use sylphie_derive::{
         SylphieModule as Module,
+        SylphieParseArg as ParseArg,
+        SylphieParseFlags as ParseFlags,
         module_impl_sylphie as module_impl,
         command, config,
     };
@@ -0,0 +1,108 @@
+//! A harness for writing integration tests against whole bots.
+
+use async_trait::async_trait;
+use crate::commands::ctx::{CommandCtx, CommandCtxImpl};
+use crate::commands::manager::CommandManager;
+use crate::core::{BootedCore, SylphieCore, SylphieEvents};
+use crate::errors::*;
+use crate::interface::InterfaceMode;
+use crate::module::Module;
+use parking_lot::Mutex;
+use static_events::prelude_async::*;
+use std::future::Future;
+use std::sync::Arc;
+use sylphie_utils::scopes::{Scope, ScopeArgs};
+use sylphie_utils::strings::StringWrapper;
+use tempfile::TempDir;
+
+/// The command context used by [`TestBot`], which records every response.
+struct TestCommandCtx {
+    scopes: Vec<Scope>,
+    raw_message: String,
+    responses: Arc<Mutex<Vec<String>>>,
+}
+#[async_trait]
+impl CommandCtxImpl for TestCommandCtx {
+    fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    fn raw_message(&self) -> &str {
+        &self.raw_message
+    }
+
+    async fn respond<E: Events>(&self, _: &Handler<E>, msg: &str) -> Result<()> {
+        self.responses.lock().push(msg.to_string());
+        Ok(())
+    }
+}
+
+/// A bot running in the current process for testing purposes.
+///
+/// The bot stores its state in a temporary directory that is deleted when it is shut down, and
+/// does not use the terminal. Log messages can be retrieved with [`TestBot::logs`].
+///
+/// Each test bot has its own directory, so tests using them may run in parallel.
+pub struct TestBot<R: Module> {
+    core: BootedCore<R>,
+    _dir: TempDir,
+}
+impl <R: Module> TestBot<R> {
+    /// Starts a new test bot with the given root module.
+    pub fn new() -> Result<Self> {
+        let dir = tempfile::Builder::new().prefix("sylphie-test-").tempdir()?;
+        let core = SylphieCore::<R>::builder("test_bot")
+            .root_path(dir.path())
+            .interface_mode(InterfaceMode::HeadlessNoInput)
+            .build()
+            .boot()?;
+        Ok(TestBot { core, _dir: dir })
+    }
+
+    /// Returns the bot's event handler.
+    pub fn handler(&self) -> &Handler<SylphieEvents<R>> {
+        self.core.handler()
+    }
+
+    /// Runs a future to completion on the bot's async runtime.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.core.block_on(future)
+    }
+
+    /// Executes a command as if it were sent from the given scopes, and returns the responses
+    /// to it.
+    ///
+    /// Scopes should be given in order from most to least specific.
+    pub fn execute_in(&self, scopes: Vec<Scope>, command: &str) -> Result<Vec<String>> {
+        let responses = Arc::new(Mutex::new(Vec::new()));
+        let ctx = CommandCtx::new(self.handler(), TestCommandCtx {
+            scopes,
+            raw_message: command.to_string(),
+            responses: responses.clone(),
+        });
+        self.block_on(self.handler().get_service::<CommandManager>().execute(&ctx))?;
+        let responses = responses.lock().clone();
+        Ok(responses)
+    }
+
+    /// Executes a command as if it were sent from the terminal, and returns the responses to it.
+    pub fn execute(&self, command: &str) -> Result<Vec<String>> {
+        let scope = Scope {
+            scope_type: StringWrapper::Static("terminal"),
+            args: ScopeArgs::None,
+        };
+        self.execute_in(vec![scope], command)
+    }
+
+    /// Returns the most recent log messages.
+    pub fn logs(&self) -> Vec<String> {
+        self.core.interface().recent_logs()
+    }
+
+    /// Shuts down the bot, waiting for its background tasks to stop.
+    ///
+    /// This also happens when the test bot is dropped.
+    pub fn shutdown(self) {
+        self.core.shutdown();
+    }
+}
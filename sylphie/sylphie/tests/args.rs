@@ -0,0 +1,129 @@
+use sylphie::prelude::*;
+use sylphie::testing::TestBot;
+
+#[derive(ParseFlags)]
+pub struct ShoutFlags {
+    #[flag(short = "l")]
+    loud: bool,
+}
+
+#[derive(ParseArg, Debug)]
+#[allow(non_camel_case_types)]
+pub enum Speed {
+    Fast,
+    SlowDown,
+    Very_Slow,
+}
+
+#[derive(Module)]
+#[module(integral)]
+pub struct ArgsModule {
+    #[module_info] info: ModuleInfo,
+}
+#[module_impl]
+impl ArgsModule {
+    #[command]
+    async fn cmd_sum(&self, ctx: &CommandCtx<impl Events>, values: Vec<u32>) -> Result<()> {
+        let sum: u32 = values.iter().sum();
+        ctx.respond(&format!("{} values, sum {}", values.len(), sum)).await
+    }
+
+    #[command]
+    async fn cmd_one(&self, ctx: &CommandCtx<impl Events>, value: u32) -> Result<()> {
+        ctx.respond(&value.to_string()).await
+    }
+
+    #[command]
+    async fn cmd_speed(&self, ctx: &CommandCtx<impl Events>, speed: Speed) -> Result<()> {
+        ctx.respond(&format!("{:?}", speed)).await
+    }
+
+    #[command]
+    async fn cmd_say(&self, ctx: &CommandCtx<impl Events>, text: Rest) -> Result<()> {
+        ctx.respond(&text).await
+    }
+
+    #[command]
+    async fn cmd_set(
+        &self, ctx: &CommandCtx<impl Events>, key: String, value: Rest,
+    ) -> Result<()> {
+        ctx.respond(&format!("{} = {}", key, value)).await
+    }
+
+    #[command]
+    async fn cmd_shout(
+        &self, ctx: &CommandCtx<impl Events>, flags: ShoutFlags, text: Rest,
+    ) -> Result<()> {
+        if flags.loud {
+            ctx.respond(&text.to_uppercase()).await
+        } else {
+            ctx.respond(&text).await
+        }
+    }
+}
+
+sylphie_root_module! {
+    module TestRoot {
+        args: ArgsModule,
+    }
+}
+
+fn check(bot: &TestBot<TestRoot>, command: &str, response: &str) {
+    assert_eq!(bot.execute(command).unwrap(), vec![response.to_string()], "{}", command);
+}
+
+#[test]
+fn vec_test() {
+    let bot = TestBot::<TestRoot>::new().unwrap();
+    check(&bot, "sum", "0 values, sum 0");
+    check(&bot, "sum 1", "1 values, sum 1");
+    check(&bot, "sum 1 2 3", "3 values, sum 6");
+    check(
+        &bot, "sum 1 x 3",
+        "Argument 2 must be an integer from 0 to 4294967295, but 'x' was given.",
+    );
+    check(&bot, "sum 1 --bogus", "Unknown flag: --bogus");
+    bot.shutdown();
+}
+
+#[test]
+fn enum_test() {
+    let bot = TestBot::<TestRoot>::new().unwrap();
+    check(&bot, "speed fast", "Fast");
+    check(&bot, "speed slow-down", "SlowDown");
+    check(&bot, "speed SLOW_DOWN", "SlowDown");
+    check(&bot, "speed very-slow", "Very_Slow");
+    check(&bot, "speed very_slow", "Very_Slow");
+    check(&bot, "speed veryslow", "Very_Slow");
+    check(
+        &bot, "speed slow",
+        "Argument 1 must be one of: fast, slow-down, very-slow, but 'slow' was given.",
+    );
+    bot.shutdown();
+}
+
+#[test]
+fn rest_test() {
+    let bot = TestBot::<TestRoot>::new().unwrap();
+    check(&bot, "say hello  \"quoted\" world ", "hello  \"quoted\" world");
+    check(&bot, "say --loud", "--loud");
+    check(&bot, "say -l hello", "-l hello");
+    check(&bot, "say", "Not enough arguments for command!");
+    check(&bot, "set key --foo bar", "key = --foo bar");
+    check(&bot, "set --foo key bar", "Unknown flag: --foo");
+    check(&bot, "shout --loud hello", "HELLO");
+    check(&bot, "shout hello -l", "HELLO");
+    check(&bot, "shout hello -l  world", "HELLO WORLD");
+    check(&bot, "shout hello --quiet", "hello --quiet");
+    bot.shutdown();
+}
+
+#[test]
+fn finish_test() {
+    let bot = TestBot::<TestRoot>::new().unwrap();
+    check(&bot, "one 1", "1");
+    check(&bot, "one 1 2", "Too many arguments were given: argument 2 ('2') was not expected.");
+    check(&bot, "one 1 --bogus", "Unknown flag: --bogus");
+    check(&bot, "one 1 -abc", "Unknown flag: -a");
+    bot.shutdown();
+}
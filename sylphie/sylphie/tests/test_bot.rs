@@ -0,0 +1,108 @@
+use sylphie::commands::module_state::ModuleStateManager;
+use sylphie::module::ModuleManager;
+use sylphie::prelude::*;
+use sylphie::testing::TestBot;
+use std::thread;
+
+#[derive(Module)]
+#[module(integral)]
+pub struct EchoModule {
+    #[module_info] info: ModuleInfo,
+}
+#[module_impl]
+impl EchoModule {
+    #[command]
+    async fn cmd_echo(&self, ctx: &CommandCtx<impl Events>, text: Rest) -> Result<()> {
+        info!("Echoing: {}", text);
+        ctx.respond(&text).await
+    }
+
+    #[command]
+    async fn cmd_echo_task(&self, ctx: &CommandCtx<impl Events>, text: Rest) -> Result<()> {
+        let message = text.0.clone();
+        tokio::spawn(async move {
+            info!("Echoing from a task: {}", message);
+        }).await.expect("Task panicked.");
+        ctx.respond(&text).await
+    }
+}
+
+pub struct PingEvent {
+    scopes: Vec<Scope>,
+    pings: u32,
+}
+self_event!(PingEvent);
+scoped_event!(PingEvent, |ev| &ev.scopes);
+
+#[derive(Module)]
+pub struct PingModule {
+    #[module_info] info: ModuleInfo,
+}
+#[module_impl]
+impl PingModule {
+    #[event_handler]
+    fn on_ping(&self, ev: &mut PingEvent) {
+        ev.pings += 1;
+    }
+}
+
+sylphie_root_module! {
+    module TestRoot {
+        echo: EchoModule,
+        ping: PingModule,
+    }
+}
+
+fn contains_line(lines: &[String], text: &str) -> bool {
+    lines.iter().any(|x| x.contains(text))
+}
+
+#[test]
+fn execute_test() {
+    let bot = TestBot::<TestRoot>::new().unwrap();
+    let responses = bot.execute("echo hello  world").unwrap();
+    assert_eq!(responses, vec!["hello  world".to_string()]);
+    assert!(contains_line(&bot.logs(), "Echoing: hello  world"));
+    assert_eq!(bot.execute("missing").unwrap(), vec!["No such command found.".to_string()]);
+    bot.shutdown();
+}
+
+fn ping(bot: &TestBot<TestRoot>, scope: &Scope) -> u32 {
+    bot.handler().dispatch_sync(PingEvent { scopes: vec![scope.clone()], pings: 0 }).pings
+}
+
+#[test]
+fn disabled_handler_test() {
+    let bot = TestBot::<TestRoot>::new().unwrap();
+    let disabled = Scope::new("test", ScopeArgs::Long(1));
+    let other = Scope::new("test", ScopeArgs::Long(2));
+    assert_eq!(ping(&bot, &disabled), 1);
+
+    let handler = bot.handler();
+    let info = handler.get_service::<ModuleManager>().find_module("ping").unwrap();
+    let state = handler.get_service::<ModuleStateManager>();
+    bot.block_on(state.set_enabled(handler, info, disabled.clone(), false)).unwrap();
+    assert_eq!(ping(&bot, &disabled), 0);
+    assert_eq!(ping(&bot, &other), 1);
+    bot.shutdown();
+}
+
+fn run_bot(name: &str, other: &str) {
+    let bot = TestBot::<TestRoot>::new().unwrap();
+    for _ in 0..10 {
+        let responses = bot.execute(&format!("echo_task hello from {}", name)).unwrap();
+        assert_eq!(responses, vec![format!("hello from {}", name)]);
+    }
+    let logs = bot.logs();
+    assert!(contains_line(&logs, &format!("Echoing from a task: hello from {}", name)));
+    assert!(!contains_line(&logs, &format!("hello from {}", other)));
+    bot.shutdown();
+}
+
+#[test]
+fn parallel_test() {
+    let first = thread::spawn(|| run_bot("first", "second"));
+    let second = thread::spawn(|| run_bot("second", "first"));
+    first.join().unwrap();
+    second.join().unwrap();
+}
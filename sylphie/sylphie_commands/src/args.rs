@@ -1,15 +1,21 @@
 use crate::commands::Command;
-use crate::ctx::{CommandArg, CommandCtx};
+use crate::ctx::{CommandArg, CommandCtx, CommandFlag};
 use static_events::prelude_async::*;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+use std::time::Duration;
 use sylphie_core::errors::*;
-
-// TODO: Implement Option/Result for variadic functions.
+use sylphie_core::i18n::Message;
+use sylphie_utils::scopes::Scope;
+use sylphie_utils::time::parse_duration;
 
 /// A helper type for parsing the arguments to command functions.
 pub struct ArgsParserCtx<'a, E: Events> {
     ctx: &'a CommandCtx<E>,
     cmd: Command,
     current_idx: usize,
+    used_flags: Vec<bool>,
 }
 impl <'a, E: Events> ArgsParserCtx<'a, E> {
     pub fn new(ctx: &'a CommandCtx<E>, cmd: Command) -> Self {
@@ -17,6 +23,7 @@ impl <'a, E: Events> ArgsParserCtx<'a, E> {
             ctx,
             cmd,
             current_idx: 1,
+            used_flags: vec![false; ctx.flags_count()],
         }
     }
 
@@ -48,9 +55,68 @@ impl <'a, E: Events> ArgsParserCtx<'a, E> {
         Ok(arg)
     }
 
+    /// Parses the current argument with a function, and increments the current argument.
+    ///
+    /// If the function returns `None`, a command error naming the position of the argument and
+    /// the expected type, such as `a number`, is returned.
+    pub fn next_arg_with<T>(
+        &mut self, expected: &str, parse: impl FnOnce(&'a str) -> Option<T>,
+    ) -> Result<T> {
+        let position = self.current_idx;
+        let arg = self.next_arg_raw()?;
+        match parse(arg.text) {
+            Some(value) => Ok(value),
+            None => cmd_error!(
+                Message::key("commands-invalid-argument")
+                    .arg("position", position)
+                    .arg("expected", expected)
+                    .arg("value", arg.text)
+            ),
+        }
+    }
+
     pub fn next_arg<T: ParseArg<'a, E>>(&mut self) -> Result<T> {
         T::produce(self)
     }
+
+    /// Marks every remaining argument as consumed.
+    pub fn consume_all(&mut self) {
+        self.current_idx = self.current_idx.max(self.ctx.args_count());
+    }
+
+    /// Returns the last flag given with any of the given names, such as `force` or `f`.
+    ///
+    /// Every flag with these names is marked as consumed.
+    pub fn take_flag(&mut self, names: &[&str]) -> Option<CommandFlag<'a>> {
+        let mut found = None;
+        for i in 0..self.ctx.flags_count() {
+            let flag = self.ctx.flag(i);
+            if names.contains(&flag.name) {
+                self.used_flags[i] = true;
+                found = Some(flag);
+            }
+        }
+        found
+    }
+
+    /// Checks that every argument and flag has been consumed, returning a command error naming
+    /// the first extra argument or unknown flag otherwise.
+    pub fn finish(&self) -> Result<()> {
+        if self.has_next_arg() {
+            let arg = self.ctx.arg(self.current_idx);
+            cmd_error!(
+                Message::key("commands-too-many-arguments")
+                    .arg("position", self.current_idx)
+                    .arg("value", arg.source_text)
+            );
+        }
+        if let Some(i) = self.used_flags.iter().position(|x| !x) {
+            cmd_error!(
+                Message::key("commands-unknown-flag").arg("flag", display_flag(self.ctx.flag(i)))
+            );
+        }
+        Ok(())
+    }
 }
 
 /// A type that can be passed into a command function from its arguments.
@@ -95,6 +161,147 @@ impl <'a, E: Events> ParseArg<'a, E> for &'a str {
     }
 }
 
+// Parsed command parameter types.
+macro_rules! parse_arg_integer {
+    ($($ty:ty),* $(,)?) => {$(
+        impl <'a, E: Events> ParseArg<'a, E> for $ty {
+            fn produce(producer: &mut ArgsParserCtx<'a, E>) -> Result<Self> {
+                let expected = format!("an integer from {} to {}", <$ty>::MIN, <$ty>::MAX);
+                producer.next_arg_with(&expected, |x| x.parse().ok())
+            }
+        }
+    )*};
+}
+parse_arg_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! parse_arg_float {
+    ($($ty:ty),* $(,)?) => {$(
+        impl <'a, E: Events> ParseArg<'a, E> for $ty {
+            fn produce(producer: &mut ArgsParserCtx<'a, E>) -> Result<Self> {
+                producer.next_arg_with("a number", |x| {
+                    x.parse::<$ty>().ok().filter(|x| x.is_finite())
+                })
+            }
+        }
+    )*};
+}
+parse_arg_float!(f32, f64);
+
+/// Parses a boolean such as `yes`, `off` or `true`.
+fn parse_bool(text: &str) -> Option<bool> {
+    match text.to_ascii_lowercase().as_str() {
+        "yes" | "y" | "on" | "true" | "enable" | "enabled" => Some(true),
+        "no" | "n" | "off" | "false" | "disable" | "disabled" => Some(false),
+        _ => None,
+    }
+}
+impl <'a, E: Events> ParseArg<'a, E> for bool {
+    fn produce(producer: &mut ArgsParserCtx<'a, E>) -> Result<Self> {
+        producer.next_arg_with("yes or no", parse_bool)
+    }
+}
+impl <'a, E: Events> ParseArg<'a, E> for Duration {
+    fn produce(producer: &mut ArgsParserCtx<'a, E>) -> Result<Self> {
+        producer.next_arg_with("a duration such as '1h30m'", |x| parse_duration(x).ok())
+    }
+}
+
+/// Scopes are parsed from the name of their type, such as `server`, and must be one of the
+/// scopes the command was used in.
+impl <'a, E: Events> ParseArg<'a, E> for Scope {
+    fn produce(producer: &mut ArgsParserCtx<'a, E>) -> Result<Self> {
+        let scopes = producer.ctx().scopes();
+        let names: Vec<_> = scopes.iter().map(|x| x.scope_type.as_str()).collect();
+        let expected = format!("one of: {}", names.join(", "));
+        producer.next_arg_with(&expected, |text| {
+            scopes.iter()
+                .find(|x| x.scope_type.as_str().eq_ignore_ascii_case(text) || x.to_string() == text)
+                .cloned()
+        })
+    }
+}
+
+/// The raw text of every remaining argument, including any quotes and the whitespace between
+/// them.
+///
+/// This is useful for commands such as `say <text...>`, where the remainder of the message
+/// should be taken as is. The text starts at the next argument or unused flag. Flags within it
+/// are included as written rather than treated as unknown flags, unless they were already
+/// taken by another parameter such as a `ParseFlags` struct.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Rest(pub String);
+impl Deref for Rest {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+impl fmt::Display for Rest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl <'a, E: Events> ParseArg<'a, E> for Rest {
+    fn produce(producer: &mut ArgsParserCtx<'a, E>) -> Result<Self> {
+        // The text starts at the earliest argument or unused flag after the last argument
+        // consumed, so flags written before the next argument are not dropped.
+        let ctx = producer.ctx;
+        let consumed_end = ctx.arg(producer.current_idx - 1).source_span.1;
+        let next_arg = if producer.has_next_arg() {
+            Some(ctx.arg(producer.current_idx).source_span.0)
+        } else {
+            None
+        };
+        let next_flag = (0..ctx.flags_count())
+            .filter(|&i| !producer.used_flags[i])
+            .map(|i| ctx.flag(i).source_span.0)
+            .filter(|&pos| pos >= consumed_end)
+            .min();
+        let start = match next_arg.into_iter().chain(next_flag).min() {
+            Some(start) => start,
+            None => cmd_error!("Not enough arguments for command!"),
+        };
+
+        // Flags already taken by another parameter are left out of the text, while any other
+        // flags within it are passed through as written.
+        let mut taken = Vec::new();
+        for i in 0..ctx.flags_count() {
+            let span = ctx.flag(i).source_span;
+            if span.0 >= start {
+                let all_taken = (0..ctx.flags_count())
+                    .filter(|&j| ctx.flag(j).source_span == span)
+                    .all(|j| producer.used_flags[j]);
+                if all_taken && !taken.contains(&span) {
+                    taken.push(span);
+                }
+            }
+        }
+        taken.sort();
+
+        producer.consume_all();
+        for i in 0..ctx.flags_count() {
+            if ctx.flag(i).source_span.0 >= start {
+                producer.used_flags[i] = true;
+            }
+        }
+        Ok(Rest(rest_text(ctx.raw_message(), start, &taken)))
+    }
+}
+
+/// Returns the text of a message from a given position, removing the given spans along with
+/// the whitespace following them.
+fn rest_text(raw: &str, start: usize, removed: &[(usize, usize)]) -> String {
+    let mut text = String::new();
+    let mut pos = start;
+    for &(span_start, span_end) in removed {
+        text.push_str(&raw[pos..span_start]);
+        let after = &raw[span_end..];
+        pos = span_end + (after.len() - after.trim_start().len());
+    }
+    text.push_str(&raw[pos..]);
+    text.trim_end().to_string()
+}
+
 // Handle optional parameters
 impl <'a, E: Events, A: ParseArg<'a, E>> ParseArg<'a, E> for Option<A> {
     fn produce(producer: &mut ArgsParserCtx<'a, E>) -> Result<Self> {
@@ -104,4 +311,95 @@ impl <'a, E: Events, A: ParseArg<'a, E>> ParseArg<'a, E> for Option<A> {
             Ok(None)
         }
     }
-}
\ No newline at end of file
+}
+
+// Handle variadic parameters
+impl <'a, E: Events, A: ParseArg<'a, E>> ParseArg<'a, E> for Vec<A> {
+    fn produce(producer: &mut ArgsParserCtx<'a, E>) -> Result<Self> {
+        let mut values = Vec::new();
+        while producer.has_next_arg() {
+            let position = producer.current_arg();
+            values.push(A::produce(producer)?);
+            if producer.current_arg() == position {
+                // `A` does not consume any arguments, so it would be repeated forever.
+                break
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// Returns how a flag is written by users, such as `--force` or `-f`.
+fn display_flag(flag: CommandFlag<'_>) -> String {
+    if flag.name.chars().count() == 1 {
+        format!("-{}", flag.name)
+    } else {
+        format!("--{}", flag.name)
+    }
+}
+
+/// A type that can be used as a field of a struct deriving `ParseFlags`.
+///
+/// Flags are named arguments such as `--force` or `--count=3`, and may be given anywhere in a
+/// command. Fields of type `bool` are set when the flag is given, and fields of type
+/// `Option<T>` are parsed from the value of flags such as `--count=3`.
+pub trait ParseFlag<'a, E: Events> : Sized {
+    /// Produces the value of a flag from the names it may be given with. The first name is the
+    /// one shown to users.
+    fn produce(producer: &mut ArgsParserCtx<'a, E>, names: &[&str]) -> Result<Self>;
+}
+impl <'a, E: Events> ParseFlag<'a, E> for bool {
+    fn produce(producer: &mut ArgsParserCtx<'a, E>, names: &[&str]) -> Result<Self> {
+        match producer.take_flag(names) {
+            Some(flag) if flag.value.is_some() => cmd_error!(
+                Message::key("commands-flag-has-value").arg("flag", display_flag(flag))
+            ),
+            Some(_) => Ok(true),
+            None => Ok(false),
+        }
+    }
+}
+impl <'a, E: Events, T: FromStr> ParseFlag<'a, E> for Option<T> {
+    fn produce(producer: &mut ArgsParserCtx<'a, E>, names: &[&str]) -> Result<Self> {
+        match producer.take_flag(names) {
+            Some(flag) => match flag.value {
+                Some(value) => match value.parse() {
+                    Ok(value) => Ok(Some(value)),
+                    Err(_) => cmd_error!(
+                        Message::key("commands-invalid-flag")
+                            .arg("flag", display_flag(flag))
+                            .arg("value", value)
+                    ),
+                },
+                None => cmd_error!(
+                    Message::key("commands-flag-needs-value").arg("flag", names[0])
+                ),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bool_test() {
+        assert_eq!(parse_bool("yes"), Some(true));
+        assert_eq!(parse_bool("ON"), Some(true));
+        assert_eq!(parse_bool("True"), Some(true));
+        assert_eq!(parse_bool("no"), Some(false));
+        assert_eq!(parse_bool("off"), Some(false));
+        assert_eq!(parse_bool("maybe"), None);
+        assert_eq!(parse_bool(""), None);
+    }
+
+    #[test]
+    fn rest_text_test() {
+        assert_eq!(rest_text("say hello world ", 4, &[]), "hello world");
+        assert_eq!(rest_text("say hello -l world", 4, &[(10, 12)]), "hello world");
+        assert_eq!(rest_text("say hello -l", 4, &[(10, 12)]), "hello");
+        assert_eq!(rest_text("say a -x b --y c", 4, &[(6, 8), (11, 14)]), "a b c");
+    }
+}
@@ -1,4 +1,5 @@
 use crate::ctx::CommandCtx;
+use crate::permissions::PermissionManager;
 use derive_setters::*;
 use futures::*;
 use futures::future::BoxFuture;
@@ -19,11 +20,15 @@ use sylphie_utils::disambiguate::EntryName;
 pub struct CommandInfo {
     /// The name of the command.
     pub name: Cow<'static, str>,
+    /// The permission required to use the command.
+    #[setters(into)]
+    pub permission: Option<Cow<'static, str>>,
 }
 impl CommandInfo {
     pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
         CommandInfo {
             name: name.into(),
+            permission: None,
         }
     }
 }
@@ -93,6 +98,14 @@ impl Command {
 
     /// Checks whether the command can be executed in a given context.
     pub async fn can_access(&self, ctx: &CommandCtx<impl Events>) -> Result<bool> {
+        if let Some(permission) = &self.0.info.permission {
+            if !ctx.is_operator() {
+                let permissions = ctx.handler().get_service::<PermissionManager>();
+                if !permissions.has_permission(permission, ctx.scopes()).await? {
+                    return Ok(false)
+                }
+            }
+        }
         self.0.command_impl.can_access(self.clone(), ctx)?.await
     }
 
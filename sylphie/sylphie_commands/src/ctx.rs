@@ -1,11 +1,15 @@
 use async_trait::*;
-use crate::raw_args::*;
+use crate::locale::LocaleManager;
+use crate::raw_args::Args;
 use static_events::prelude_async::*;
 use std::any::Any;
 use std::sync::Arc;
+use sylphie_core::i18n::Message;
 use sylphie_core::prelude::*;
 use sylphie_utils::scopes::*;
 
+pub use crate::raw_args::ArgParsingOptions;
+
 /// The implementation of a command context.
 #[async_trait]
 pub trait CommandCtxImpl: Sync + Send + 'static {
@@ -22,6 +26,12 @@ pub trait CommandCtxImpl: Sync + Send + 'static {
     /// This should return the same value for every call.
     fn raw_message(&self) -> &str;
 
+    /// Returns whether commands in this context are used by the bot's operator, such as from
+    /// the terminal. Operators may use every permission.
+    fn is_operator(&self) -> bool {
+        false
+    }
+
     /// Responds to the user with a given string.
     async fn respond<E: Events>(&self, target: &Handler<E>, msg: &str) -> Result<()>;
 }
@@ -38,19 +48,42 @@ pub struct CommandArg<'a> {
     pub text: &'a str,
 }
 
+/// A flag passed to a command, such as `--force`, `--key=value` or each letter of `-abc`.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub struct CommandFlag<'a> {
+    /// The original source span the flag originated from.
+    pub source_span: (usize, usize),
+    /// The original text of the flag.
+    pub source_text: &'a str,
+    /// The name of the flag, without any leading dashes.
+    pub name: &'a str,
+    /// The parsed value of the flag, if one was given with `--key=value`.
+    pub value: Option<&'a str>,
+}
+
 /// The context for a given command.
 pub struct CommandCtx<E: Events>(Arc<CommandCtxData<E>>);
 struct CommandCtxData<E: Events> {
     handle: Handler<E>,
+    start: usize,
     args: Args,
     ctx_impl: Box<dyn CommandCtxImplWrapper<E>>,
 }
 impl <E: Events> CommandCtx<E> {
     /// Creates a new command context given an implementation and a [`Handler`].
     pub fn new(core: &Handler<E>, ctx_impl: impl CommandCtxImpl) -> Self {
-        let args = Args::parse(ctx_impl.args_parsing_options(), ctx_impl.raw_message());
+        Self::new_stripped(core, ctx_impl, 0)
+    }
+
+    /// Creates a new command context, ignoring a prefix of the given length at the start of the
+    /// raw message.
+    pub fn new_stripped(core: &Handler<E>, ctx_impl: impl CommandCtxImpl, start: usize) -> Self {
+        let raw_message = &ctx_impl.raw_message()[start..];
+        let args = Args::parse(ctx_impl.args_parsing_options(), raw_message);
         CommandCtx(Arc::new(CommandCtxData {
             handle: core.clone(),
+            start,
             args,
             ctx_impl: Box::new(ctx_impl),
         }))
@@ -68,9 +101,9 @@ impl <E: Events> CommandCtx<E> {
         self.0.ctx_impl.as_any().downcast_ref::<T>()
     }
 
-    /// Returns the raw text of the command.
+    /// Returns the raw text of the command, without any prefix it was sent with.
     pub fn raw_message(&self) -> &str {
-        self.0.ctx_impl.raw_message()
+        &self.0.ctx_impl.raw_message()[self.0.start..]
     }
 
     /// Returns the number of arguments passed to this function.
@@ -98,15 +131,51 @@ impl <E: Events> CommandCtx<E> {
         }
     }
 
+    /// Returns the number of flags passed to this function.
+    pub fn flags_count(&self) -> usize {
+        self.0.args.flags_len()
+    }
+
+    /// Returns a flag passed to this function.
+    pub fn flag(&self, i: usize) -> CommandFlag<'_> {
+        let source = self.raw_message();
+        let source_span = self.0.args.flag_source_span(i);
+        CommandFlag {
+            source_span,
+            source_text: &source[source_span.0..source_span.1],
+            name: self.0.args.flag_name(i),
+            value: self.0.args.flag_value(source, i),
+        }
+    }
+
     /// Returns the scopes this event occured in, in order from most to least specific.
     pub fn scopes(&self) -> &[Scope] {
         self.0.ctx_impl.scopes()
     }
 
+    /// Returns whether this command is used by the bot's operator, who may use every
+    /// permission.
+    pub fn is_operator(&self) -> bool {
+        self.0.ctx_impl.is_operator()
+    }
+
     /// Responds to the user with a given string.
     pub async fn respond(&self, msg: &str) -> Result<()> {
         self.0.ctx_impl.respond(&self.0.handle, msg).await
     }
+
+    /// Renders a message in the locale configured for the scopes this command was sent from.
+    pub async fn render(&self, msg: &Message) -> Result<String> {
+        let locales = self.handler().get_service::<LocaleManager>();
+        locales.render_for(self.handler(), self.scopes(), msg).await
+    }
+
+    /// Responds to the user with a message, localized for the scopes this command was sent
+    /// from.
+    pub async fn respond_msg(&self, msg: impl Into<Message>) -> Result<()> {
+        let text = self.render(&msg.into()).await?;
+        self.respond(&text).await
+    }
 }
 impl <E: Events> Clone for CommandCtx<E> {
     fn clone(&self) -> Self {
@@ -121,6 +190,7 @@ trait CommandCtxImplWrapper<E: Events>: Sync + Send + 'static {
     fn raw_message(&self) -> &str;
 
     fn scopes(&self) -> &[Scope];
+    fn is_operator(&self) -> bool;
     async fn respond(&self, target: &Handler<E>, msg: &str) -> Result<()>;
 }
 #[async_trait]
@@ -129,6 +199,7 @@ impl <E: Events, T: CommandCtxImpl> CommandCtxImplWrapper<E> for T {
     fn raw_message(&self) -> &str { self.raw_message() }
 
     fn scopes(&self) -> &[Scope] { self.scopes() }
+    fn is_operator(&self) -> bool { self.is_operator() }
     async fn respond(&self, target: &Handler<E>, msg: &str) -> Result<()> {
         self.respond(target, msg).await
     }
@@ -0,0 +1,109 @@
+//! Recognizes commands in messages received from connections, and executes them.
+
+use crate::ctx::{CommandCtx, CommandCtxImpl};
+use crate::manager::CommandManager;
+use static_events::prelude_async::*;
+use sylphie_core::derives::*;
+use sylphie_core::prelude::*;
+use sylphie_database::config::*;
+use sylphie_utils::scopes::Scope;
+
+/// A message received from a connection, which may contain a command.
+pub trait IncomingMessage: CommandCtxImpl {
+    /// Returns the ways the bot can be mentioned at the start of this message, such as
+    /// `<@1234>` on Discord.
+    ///
+    /// Messages starting with a mention are treated as commands, regardless of the configured
+    /// prefix.
+    fn mention_prefixes(&self) -> &[String] {
+        &[]
+    }
+}
+
+/// Returns the length of the command prefix a message starts with, if it contains a command.
+fn find_command_start(message: &str, prefix: &str, mentions: &[String]) -> Option<usize> {
+    let start = if let Some(mention) = mentions.iter()
+        .find(|x| !x.is_empty() && message.starts_with(x.as_str()))
+    {
+        let rest = &message[mention.len()..];
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return None
+        }
+        message.len() - rest.trim_start().len()
+    } else if !prefix.is_empty() && message.starts_with(prefix) {
+        prefix.len()
+    } else {
+        return None
+    };
+    if message[start..].trim().is_empty() {
+        None
+    } else {
+        Some(start)
+    }
+}
+
+/// The service that executes commands found in messages received from connections.
+///
+/// Connection modules should pass each message they receive to
+/// [`MessageDispatcher::dispatch_message`], rather than parsing commands themselves.
+#[derive(Module)]
+#[module(component, requires(ConfigManager))]
+pub struct MessageDispatcher {
+    #[module_info] info: ModuleInfo,
+}
+#[module_impl]
+impl MessageDispatcher {
+    #[config]
+    pub const CFG_PREFIX: ConfigKey<String> = config_option!(
+        Any, "prefix f1733370-515d-43f8-87b4-8b2833cfdd9d", || "!".to_string(),
+    );
+
+    /// Returns the command prefix configured for the given scopes.
+    ///
+    /// The scopes should be in order from most to least specific.
+    pub async fn prefix(&self, target: &Handler<impl Events>, scopes: &[Scope]) -> Result<String> {
+        let config = target.get_service::<ConfigManager>();
+        config.get_resolved(target, scopes, Self::CFG_PREFIX).await
+    }
+
+    /// Executes a message as a command if it starts with the configured prefix or a mention of
+    /// the bot.
+    ///
+    /// Returns whether the message contained a command.
+    pub async fn dispatch_message<E: Events>(
+        &self, target: &Handler<E>, message: impl IncomingMessage,
+    ) -> Result<bool> {
+        let prefix = self.prefix(target, message.scopes()).await?;
+        let start = find_command_start(
+            message.raw_message(), &prefix, message.mention_prefixes(),
+        );
+        match start {
+            Some(start) => {
+                let ctx = CommandCtx::new_stripped(target, message, start);
+                target.get_service::<CommandManager>().execute(&ctx).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_test() {
+        let mentions = ["<@1234>".to_string(), "<@!1234>".to_string()];
+        assert_eq!(find_command_start("!help", "!", &mentions), Some(1));
+        assert_eq!(find_command_start("bot, help", "bot,", &mentions), Some(4));
+        assert_eq!(find_command_start("<@1234> help", "!", &mentions), Some(8));
+        assert_eq!(find_command_start("<@!1234>   help", "!", &mentions), Some(11));
+        assert_eq!(find_command_start("<@1234>help", "!", &mentions), None);
+        assert_eq!(find_command_start("<@1234>", "!", &mentions), None);
+        assert_eq!(find_command_start("!", "!", &mentions), None);
+        assert_eq!(find_command_start("! ", "!", &mentions), None);
+        assert_eq!(find_command_start("help", "!", &mentions), None);
+        assert_eq!(find_command_start("help", "", &[]), None);
+    }
+}
@@ -0,0 +1,296 @@
+//! Tracks errors and panics in commands, and temporarily disables commands and modules that fail
+//! repeatedly.
+
+use arc_swap::ArcSwap;
+use crate::commands::Command;
+use crate::module_state::ModuleStateManager;
+use derive_setters::*;
+use parking_lot::Mutex;
+use static_events::prelude_async::*;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use sylphie_core::core::CollectInfoEvent;
+use sylphie_core::derives::*;
+use sylphie_core::i18n::Message;
+use sylphie_core::module::{ModuleFlag, ModuleId, ModuleManager};
+use sylphie_core::prelude::*;
+use sylphie_utils::time::format_duration;
+
+/// Settings for the circuit breaker used to disable failing commands and modules.
+#[derive(Clone, Debug, Setters)]
+#[non_exhaustive]
+pub struct CircuitBreakerSettings {
+    /// The number of failures within `window` that cause a command or module to be disabled.
+    ///
+    /// Setting this to zero disables the circuit breaker.
+    pub max_failures: u32,
+    /// The period of time failures are counted over.
+    pub window: Duration,
+    /// How long a command or module is disabled for once it has failed too many times.
+    pub cooldown: Duration,
+}
+impl Default for CircuitBreakerSettings {
+    fn default() -> Self {
+        CircuitBreakerSettings {
+            max_failures: 5,
+            window: Duration::from_secs(60),
+            cooldown: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// The number of failures recorded for a command or module.
+#[derive(Copy, Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct HealthStats {
+    /// The number of errors returned, not including command errors or panics.
+    pub errors: u64,
+    /// The number of panics caught.
+    pub panics: u64,
+    /// How much longer the command or module is disabled for by the circuit breaker.
+    pub disabled_for: Option<Duration>,
+}
+impl HealthStats {
+    /// Returns whether no failures have been recorded.
+    pub fn is_healthy(&self) -> bool {
+        self.errors == 0 && self.panics == 0
+    }
+}
+
+#[derive(Default)]
+struct HealthData {
+    errors: u64,
+    panics: u64,
+    recent_failures: VecDeque<Instant>,
+    disabled_until: Option<Instant>,
+}
+impl HealthData {
+    fn disabled_for(&self, now: Instant) -> Option<Duration> {
+        match self.disabled_until {
+            Some(until) if until > now => Some(until - now),
+            _ => None,
+        }
+    }
+
+    fn stats(&self, now: Instant) -> HealthStats {
+        HealthStats {
+            errors: self.errors,
+            panics: self.panics,
+            disabled_for: self.disabled_for(now),
+        }
+    }
+
+    /// Records a failure, and returns whether the circuit breaker was tripped by it.
+    fn record_failure(
+        &mut self, settings: &CircuitBreakerSettings, is_panic: bool, can_trip: bool, now: Instant,
+    ) -> bool {
+        if is_panic {
+            self.panics += 1;
+        } else {
+            self.errors += 1;
+        }
+
+        if settings.max_failures == 0 || !can_trip {
+            return false
+        }
+        self.recent_failures.push_back(now);
+        while let Some(time) = self.recent_failures.front() {
+            if now.duration_since(*time) < settings.window {
+                break
+            }
+            self.recent_failures.pop_front();
+        }
+        if self.recent_failures.len() >= settings.max_failures as usize {
+            self.recent_failures.clear();
+            self.disabled_until = Some(now + settings.cooldown);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn stats_for<K: Eq + Hash>(map: &Mutex<HashMap<K, HealthData>>, key: &K) -> HealthStats {
+    let now = Instant::now();
+    map.lock().get(key).map_or_else(Default::default, |x| x.stats(now))
+}
+
+/// The service that counts the errors and panics caused by each command and module.
+///
+/// Command errors are not counted, as they are meant to be shown to users. When a command or
+/// a module fails too many times in a short period, it is temporarily disabled as configured
+/// with [`HealthManager::set_circuit_breaker`]. Integral modules are never disabled this way,
+/// though their commands may still be.
+#[derive(Module)]
+#[module(component)]
+pub struct HealthManager {
+    #[module_info] info: ModuleInfo,
+    settings: ArcSwap<CircuitBreakerSettings>,
+    modules: Mutex<HashMap<ModuleId, HealthData>>,
+    commands: Mutex<HashMap<String, HealthData>>,
+}
+#[module_impl]
+impl HealthManager {
+    #[event_handler]
+    fn collect_info(&self, target: &Handler<impl Events>, ev: &mut CollectInfoEvent) {
+        ev.add_line("Module health:");
+        for module in target.get_service::<ModuleManager>().loaded_modules() {
+            if module.metadata().flags.contains(ModuleFlag::Anonymous) {
+                continue
+            }
+            let stats = self.module_stats(module.id());
+            let mut line = if stats.is_healthy() {
+                format!("    {}: healthy", module.name())
+            } else {
+                format!("    {}: {} errors, {} panics", module.name(), stats.errors, stats.panics)
+            };
+            if let Some(remaining) = stats.disabled_for {
+                line.push_str(&format!(" (disabled for {})", format_duration(remaining)));
+            }
+            ev.add_line(line);
+        }
+    }
+
+    /// Sets the settings used for the circuit breaker.
+    pub fn set_circuit_breaker(&self, settings: CircuitBreakerSettings) {
+        self.settings.store(Arc::new(settings));
+    }
+
+    /// Returns the failures recorded for a module.
+    pub fn module_stats(&self, module: ModuleId) -> HealthStats {
+        stats_for(&self.modules, &module)
+    }
+
+    /// Returns the failures recorded for a command.
+    pub fn command_stats(&self, command: &Command) -> HealthStats {
+        stats_for(&self.commands, &command.full_name().to_string())
+    }
+
+    /// Returns an error message explaining why a command is unavailable, if it has been
+    /// disabled by the circuit breaker.
+    pub fn check_disabled(&self, command: &Command) -> Option<Message> {
+        let now = Instant::now();
+        if let Some(info) = command.module_info() {
+            if let Some(remaining) = self.modules.lock().get(&info.id())
+                .and_then(|x| x.disabled_for(now))
+            {
+                return Some(Message::key("commands-module-tripped")
+                    .arg("module", info.name())
+                    .arg("remaining", format_duration(remaining)))
+            }
+        }
+        if let Some(remaining) = self.commands.lock().get(command.full_name())
+            .and_then(|x| x.disabled_for(now))
+        {
+            return Some(Message::key("commands-command-tripped")
+                .arg("command", command.full_name())
+                .arg("remaining", format_duration(remaining)))
+        }
+        None
+    }
+
+    /// Records that a command failed with an error or a panic.
+    pub fn record_failure(&self, target: &Handler<impl Events>, command: &Command, err: &Error) {
+        let settings = self.settings.load();
+        let is_panic = match err.error_kind() {
+            ErrorKind::CommandError(_) => return,
+            ErrorKind::Panicked(..) => true,
+            _ => false,
+        };
+        let now = Instant::now();
+
+        let tripped = self.commands.lock()
+            .entry(command.full_name().to_string())
+            .or_default()
+            .record_failure(&settings, is_panic, true, now);
+        if tripped {
+            warn!(
+                "Command '{}' has been disabled for {} due to repeated errors.",
+                command.full_name(), format_duration(settings.cooldown),
+            );
+        }
+
+        if let Some(info) = command.module_info() {
+            let module_state = target.get_service::<ModuleStateManager>();
+            let can_trip = !module_state.is_integral(target, info);
+            let tripped = self.modules.lock()
+                .entry(info.id())
+                .or_default()
+                .record_failure(&settings, is_panic, can_trip, now);
+            if tripped {
+                warn!(
+                    "Module '{}' has been disabled for {} due to repeated errors.",
+                    info.name(), format_duration(settings.cooldown),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(max_failures: u32) -> CircuitBreakerSettings {
+        CircuitBreakerSettings::default()
+            .max_failures(max_failures)
+            .window(Duration::from_secs(60))
+            .cooldown(Duration::from_secs(300))
+    }
+
+    #[test]
+    fn trip_test() {
+        let settings = settings(3);
+        let start = Instant::now();
+        let mut data = HealthData::default();
+        assert!(!data.record_failure(&settings, false, true, start));
+        assert!(!data.record_failure(&settings, true, true, start + Duration::from_secs(1)));
+        assert_eq!(data.disabled_for(start + Duration::from_secs(2)), None);
+        assert!(data.record_failure(&settings, false, true, start + Duration::from_secs(2)));
+
+        let stats = data.stats(start + Duration::from_secs(2));
+        assert_eq!((stats.errors, stats.panics), (2, 1));
+        assert_eq!(stats.disabled_for, Some(Duration::from_secs(300)));
+        assert_eq!(data.disabled_for(start + Duration::from_secs(302)), None);
+
+        // the failures are cleared once the circuit breaker trips
+        assert!(!data.record_failure(&settings, false, true, start + Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn window_test() {
+        let settings = settings(3);
+        let start = Instant::now();
+        let mut data = HealthData::default();
+        assert!(!data.record_failure(&settings, false, true, start));
+        assert!(!data.record_failure(&settings, false, true, start + Duration::from_secs(30)));
+        // the first failure is no longer within the window
+        assert!(!data.record_failure(&settings, false, true, start + Duration::from_secs(60)));
+        assert!(data.record_failure(&settings, false, true, start + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn disabled_test() {
+        let start = Instant::now();
+
+        let breaker_off = settings(0);
+        let mut data = HealthData::default();
+        for i in 0..10 {
+            let now = start + Duration::from_secs(i);
+            assert!(!data.record_failure(&breaker_off, false, true, now));
+        }
+        assert_eq!(data.stats(start).errors, 10);
+
+        // integral modules can't be disabled by the circuit breaker
+        let breaker = settings(1);
+        let mut data = HealthData::default();
+        for i in 0..10 {
+            let now = start + Duration::from_secs(i);
+            assert!(!data.record_failure(&breaker, true, false, now));
+        }
+        assert_eq!(data.stats(start).panics, 10);
+        assert_eq!(data.disabled_for(start), None);
+    }
+}
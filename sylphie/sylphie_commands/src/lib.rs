@@ -3,23 +3,57 @@
 pub mod args;
 pub mod commands;
 pub mod ctx;
+pub mod dispatch;
+pub mod health;
+pub mod locale;
+pub mod log_levels;
 pub mod manager;
 mod module;
+pub mod module_state;
+pub mod permissions;
 mod raw_args;
 
 pub use module::CommandsModule;
 
 /// A convenience module containing common imports.
 pub mod prelude {
+    pub use crate::args::Rest;
     pub use crate::commands::{Command, CommandInfo};
     pub use crate::ctx::{CommandCtx, CommandArg};
 }
 
+/// Implements [`FilteredEvent`](sylphie_core::module::FilteredEvent) for an event that occurs
+/// in particular scopes, so the event handlers of modules disabled in those scopes are skipped.
+///
+/// The expression is given the event, and should return its scopes in order from most to
+/// least specific.
+///
+/// ```ignore
+/// scoped_event!(MessageReceivedEvent, |ev| &ev.scopes);
+/// ```
+#[macro_export]
+macro_rules! scoped_event {
+    ($ty:ty, |$ev:ident| $scopes:expr $(,)?) => {
+        impl $crate::__macro_export::FilteredEvent for $ty {
+            fn is_enabled_for(
+                &self,
+                target: &$crate::__macro_export::Handler<impl $crate::__macro_export::Events>,
+                module: &$crate::__macro_export::ModuleInfo,
+            ) -> bool {
+                let $ev = self;
+                $crate::__macro_priv::is_enabled_in(target, module, $scopes)
+            }
+        }
+    };
+}
+
 /// Reexports of various types for macros. Not public API.
 #[doc(hidden)]
 pub mod __macro_export {
     pub use futures::FutureExt;
     pub use futures::future::BoxFuture;
+    pub use static_events::prelude_async::{Events, Handler};
+    pub use sylphie_core::module::{FilteredEvent, ModuleInfo};
 }
 
 /// Various utility functions and types for macros. Not public API.
@@ -27,10 +61,12 @@ pub mod __macro_export {
 pub mod __macro_priv {
     use crate::commands::Command;
     use crate::ctx::CommandCtx;
+    use crate::module_state::ModuleStateManager;
     use static_events::prelude_async::*;
     use std::marker::PhantomData;
     use sylphie_core::*;
-    use sylphie_core::module::ModuleId;
+    use sylphie_core::module::{ModuleId, ModuleInfo};
+    use sylphie_utils::scopes::Scope;
 
     pub struct ExecuteCommand<T, E: Events> {
         pub mod_id: ModuleId,
@@ -50,6 +86,18 @@ pub mod __macro_priv {
         }
     }
 
+    /// Checks whether a module is enabled for `scoped_event!`.
+    pub fn is_enabled_in(
+        target: &Handler<impl Events>, module: &ModuleInfo, scopes: &[Scope],
+    ) -> bool {
+        target.get_service::<ModuleStateManager>().is_enabled(target, module, scopes)
+    }
+
+    /// Normalizes the name of an enum variant for `#[derive(ParseArg)]`.
+    pub fn normalize_enum_arg(text: &str) -> String {
+        text.chars().filter(|x| *x != '-' && *x != '_').flat_map(char::to_lowercase).collect()
+    }
+
     #[inline(never)] #[cold]
     pub fn duplicate_module_id() -> ! {
         panic!("Duplicate module ID!!")
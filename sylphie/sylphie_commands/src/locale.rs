@@ -0,0 +1,110 @@
+//! Selects the locale used for each scope, and renders localized messages.
+
+use arc_swap::ArcSwap;
+use static_events::prelude_async::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use sylphie_core::core::InitEvent;
+use sylphie_core::derives::*;
+use sylphie_core::i18n::{Catalog, Message};
+use sylphie_core::prelude::*;
+use sylphie_database::config::*;
+use sylphie_utils::scopes::Scope;
+
+/// The locale used when no other locale is configured, and when a message is missing from the
+/// catalog of the configured locale.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// The event used to register message catalogs.
+///
+/// Crates should bundle their catalogs with `include_str!`, and register them from their module.
+#[derive(Debug, Default)]
+pub struct RegisterCatalogsEvent {
+    catalogs: HashMap<String, Catalog>,
+}
+self_event!(RegisterCatalogsEvent);
+impl RegisterCatalogsEvent {
+    /// Registers a message catalog for a given locale, such as `en` or `pt-BR`.
+    ///
+    /// Catalogs registered for the same locale are merged together.
+    pub fn add_catalog(&mut self, locale: &str, source: &str) {
+        match Catalog::parse(source) {
+            Ok(catalog) => self.catalogs.entry(locale.to_ascii_lowercase())
+                .or_default()
+                .extend(catalog),
+            Err(e) => error!("Could not parse message catalog for locale '{}': {}", locale, e),
+        }
+    }
+}
+
+/// The service that selects locales and renders localized messages.
+///
+/// The locale for a scope is set with the `locale` configuration option, and is resolved
+/// through [`ConfigManager::get_resolved`].
+#[derive(Module)]
+#[module(component, requires(ConfigManager))]
+pub struct LocaleManager {
+    #[module_info] info: ModuleInfo,
+    catalogs: ArcSwap<HashMap<String, Catalog>>,
+}
+#[module_impl]
+impl LocaleManager {
+    #[config]
+    pub const CFG_LOCALE: ConfigKey<String> = config_option!(
+        Any, "locale 46bd5566-56b5-4647-a6bd-9c868ff962dc", || DEFAULT_LOCALE.to_string(),
+    );
+
+    #[event_handler]
+    fn init_catalogs(&self, target: &Handler<impl Events>, _: &InitEvent) {
+        self.reload(target);
+    }
+
+    #[event_handler]
+    fn register_catalogs(ev: &mut RegisterCatalogsEvent) {
+        ev.add_catalog("en", include_str!("../i18n/en.lang"));
+    }
+
+    /// Reloads the message catalogs.
+    pub fn reload(&self, target: &Handler<impl Events>) {
+        let ev = target.dispatch_sync(RegisterCatalogsEvent::default());
+        self.catalogs.store(Arc::new(ev.catalogs));
+    }
+
+    /// Returns the locale configured for the given scopes.
+    ///
+    /// The scopes should be in order from most to least specific.
+    pub async fn locale(&self, target: &Handler<impl Events>, scopes: &[Scope]) -> Result<String> {
+        let config = target.get_service::<ConfigManager>();
+        config.get_resolved(target, scopes, Self::CFG_LOCALE).await
+    }
+
+    /// Renders a message in the given locale.
+    ///
+    /// Messages missing from the locale's catalog are looked up in the catalog for its
+    /// language (`en` for `en-GB`), then in the catalog for [`DEFAULT_LOCALE`].
+    pub fn render(&self, locale: &str, msg: &Message) -> String {
+        let catalogs = self.catalogs.load();
+        let locale = locale.to_ascii_lowercase();
+        let language = locale.split('-').next().unwrap_or("");
+
+        let mut chain = Vec::new();
+        for name in &[locale.as_str(), language, DEFAULT_LOCALE] {
+            if let Some(catalog) = catalogs.get(*name) {
+                if !chain.iter().any(|x| std::ptr::eq(*x, catalog)) {
+                    chain.push(catalog);
+                }
+            }
+        }
+        msg.render(&chain)
+    }
+
+    /// Renders a message in the locale configured for the given scopes.
+    pub async fn render_for(
+        &self, target: &Handler<impl Events>, scopes: &[Scope], msg: &Message,
+    ) -> Result<String> {
+        match msg {
+            Message::Text(text) => Ok(text.to_string()),
+            _ => Ok(self.render(&self.locale(target, scopes).await?, msg)),
+        }
+    }
+}
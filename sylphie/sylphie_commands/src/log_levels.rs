@@ -0,0 +1,230 @@
+//! Allows logging directives to be changed at runtime with the `.loglevel` built-in command.
+
+use parking_lot::RwLock;
+use serde::*;
+use static_events::prelude_async::*;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sylphie_core::core::{BuiltinCommandEvent, InitEvent, RegisterBuiltinCommandsEvent};
+use sylphie_core::derives::*;
+use sylphie_core::interface::{Interface, SetupLoggerEvent, check_log_directive};
+use sylphie_core::prelude::*;
+use sylphie_core::timer::{Schedule, Timer};
+use sylphie_database::serializable::SimpleSerialize;
+use sylphie_database::singleton::SingletonStore;
+use sylphie_utils::time::{format_duration, parse_duration};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct LogDirective {
+    directive: String,
+    /// The time this directive expires at, in seconds since the Unix epoch.
+    expires: Option<u64>,
+}
+impl LogDirective {
+    /// Returns the part of the directive that selects which messages it applies to.
+    fn selector(&self) -> &str {
+        match self.directive.rfind('=') {
+            Some(i) => &self.directive[..i],
+            None => "",
+        }
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires.map_or(false, |x| x <= now)
+    }
+}
+
+/// The longest duration a logging directive may be set for.
+///
+/// This keeps expiry times representable, and within the range the timer can schedule.
+const MAX_DURATION: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |x| x.as_secs())
+}
+
+/// The service that stores the logging directives set with `.loglevel`.
+///
+/// These directives are saved in the database, and are applied to both the console and the log
+/// files after the directives set by other modules.
+#[derive(Module)]
+#[module(component)]
+pub struct LogLevelManager {
+    #[module_info] info: ModuleInfo,
+    #[submodule] store: SingletonStore<SimpleSerialize<Vec<LogDirective>>>,
+    directives: RwLock<Vec<LogDirective>>,
+}
+#[module_impl]
+impl LogLevelManager {
+    #[event_handler]
+    async fn load_directives(&self, target: &Handler<impl Events>, _: &InitEvent) -> Result<()> {
+        let now = now();
+        let stored = self.store.get().await.0;
+        let directives: Vec<_> = stored.iter().filter(|x| !x.is_expired(now)).cloned().collect();
+        if directives.len() != stored.len() {
+            self.store.set(SimpleSerialize(directives.clone())).await?;
+        }
+        for directive in &directives {
+            if let Some(expires) = directive.expires {
+                self.schedule_expiry(target, Duration::from_secs(expires - now))?;
+            }
+        }
+        *self.directives.write() = directives;
+        Ok(())
+    }
+
+    #[event_handler(EvAfterEvent)]
+    fn setup_logger(&self, ev: &mut SetupLoggerEvent) {
+        let now = now();
+        for directive in &*self.directives.read() {
+            if !directive.is_expired(now) {
+                ev.add_console_directive(&directive.directive);
+                ev.add_file_directive(&directive.directive);
+            }
+        }
+    }
+
+    #[event_handler]
+    fn register_builtin_commands(ev: &mut RegisterBuiltinCommandsEvent) {
+        ev.register(".loglevel", "Lists the logging directives set at runtime.");
+        ev.register(
+            ".loglevel set <directive> [duration]",
+            "Sets a logging directive such as 'sylphie_database=trace', optionally only for a \
+             duration such as '10m'.",
+        );
+        ev.register(".loglevel remove <directive>", "Removes a logging directive.");
+        ev.register(".loglevel clear", "Removes all logging directives set at runtime.");
+    }
+
+    #[event_handler]
+    async fn loglevel_command(&self, target: &Handler<impl Events>, ev: &BuiltinCommandEvent) {
+        if ev.name() != "loglevel" {
+            return
+        }
+        ev.set_handled();
+        if let Err(e) = self.run_command(target, ev.args()).await {
+            match e.error_kind() {
+                ErrorKind::CommandError(msg) => error!(target: "[term]", "{}", msg),
+                _ => e.report_error(),
+            }
+        }
+    }
+
+    async fn run_command(&self, target: &Handler<impl Events>, args: &str) -> Result<()> {
+        let args: Vec<_> = args.split_whitespace().collect();
+        match args.as_slice() {
+            [] => {
+                let now = now();
+                let directives = self.directives.read();
+                if directives.is_empty() {
+                    info!(target: "[term]", "No logging directives have been set at runtime.");
+                } else {
+                    info!(target: "[term]", "Logging directives:");
+                }
+                for directive in directives.iter().filter(|x| !x.is_expired(now)) {
+                    match directive.expires {
+                        Some(expires) => {
+                            let remaining = format_duration(Duration::from_secs(expires - now));
+                            info!(
+                                target: "[term]", "    {} (expires in {})",
+                                directive.directive, remaining,
+                            );
+                        }
+                        None => info!(target: "[term]", "    {}", directive.directive),
+                    }
+                }
+            }
+            ["set", directive] => self.set_directive(target, directive, None).await?,
+            ["set", directive, duration] => {
+                let duration = parse_duration(duration)?;
+                self.set_directive(target, directive, Some(duration)).await?;
+            }
+            ["remove", directive] => {
+                let removed = self.update(target, |directives| {
+                    let len = directives.len();
+                    directives.retain(|x| x.directive != *directive && x.selector() != *directive);
+                    directives.len() != len
+                }).await?;
+                if removed {
+                    info!(target: "[term]", "Removed logging directive '{}'.", directive);
+                } else {
+                    cmd_error!("No logging directive '{}' has been set.", directive);
+                }
+            }
+            ["clear"] => {
+                self.update(target, |directives| directives.clear()).await?;
+                info!(target: "[term]", "Removed all logging directives set at runtime.");
+            }
+            _ => cmd_error!("Unknown arguments for '.loglevel'. Use '.help' for usage."),
+        }
+        Ok(())
+    }
+
+    async fn set_directive(
+        &self, target: &Handler<impl Events>, directive: &str, duration: Option<Duration>,
+    ) -> Result<()> {
+        check_log_directive(directive)?;
+        if let Some(duration) = duration {
+            if duration > MAX_DURATION {
+                cmd_error!(
+                    "Logging directives cannot be set for longer than {}.",
+                    format_duration(MAX_DURATION),
+                );
+            }
+        }
+        let new = LogDirective {
+            directive: directive.to_string(),
+            expires: duration.map(|x| now() + x.as_secs()),
+        };
+        self.update(target, |directives| {
+            directives.retain(|x| x.selector() != new.selector());
+            directives.push(new.clone());
+        }).await?;
+        match duration {
+            Some(duration) => {
+                self.schedule_expiry(target, duration)?;
+                info!(
+                    target: "[term]", "Set logging directive '{}' for {}.",
+                    directive, format_duration(duration),
+                );
+            }
+            None => info!(target: "[term]", "Set logging directive '{}'.", directive),
+        }
+        Ok(())
+    }
+
+    /// Modifies the stored directives, saves them, and reloads the logger to apply them.
+    async fn update<T>(
+        &self, target: &Handler<impl Events>, func: impl FnOnce(&mut Vec<LogDirective>) -> T,
+    ) -> Result<T> {
+        let (result, directives) = {
+            let mut directives = self.directives.write();
+            let result = func(&mut directives);
+            (result, directives.clone())
+        };
+        self.store.set(SimpleSerialize(directives)).await?;
+        target.get_service::<Interface>().reload_logger(target)?;
+        Ok(result)
+    }
+
+    fn schedule_expiry(&self, target: &Handler<impl Events>, delay: Duration) -> Result<()> {
+        target.get_service::<Timer>().schedule(target, Schedule::After(delay), |target| async move {
+            target.get_service::<LogLevelManager>().remove_expired(&target).await
+        })?;
+        Ok(())
+    }
+
+    async fn remove_expired(&self, target: &Handler<impl Events>) -> Result<()> {
+        let now = now();
+        let expired: Vec<_> = self.directives.read().iter()
+            .filter(|x| x.is_expired(now))
+            .map(|x| x.directive.clone())
+            .collect();
+        if !expired.is_empty() {
+            self.update(target, |directives| directives.retain(|x| !x.is_expired(now))).await?;
+            for directive in expired {
+                info!("Logging directive '{}' has expired.", directive);
+            }
+        }
+        Ok(())
+    }
+}
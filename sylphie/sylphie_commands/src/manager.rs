@@ -1,10 +1,15 @@
 use arc_swap::ArcSwapOption;
 use crate::commands::Command;
 use crate::ctx::CommandCtx;
+use crate::health::HealthManager;
+use crate::module_state::ModuleStateManager;
 use static_events::prelude_async::*;
 use std::sync::Arc;
 use sylphie_core::errors::*;
+use sylphie_core::i18n::Message;
+use sylphie_core::watchdog::Watchdog;
 use sylphie_utils::disambiguate::{DisambiguatedSet, Disambiguated, LookupResult};
+use tracing_futures::Instrument;
 
 /// The event used to register commands.
 #[derive(Debug, Default)]
@@ -75,8 +80,14 @@ impl CommandManager {
         let data = self.0.data.load();
         let data = data.as_ref().map_or(&self.0.null, |x| &*x);
 
+        let module_state = ctx.handler().get_service::<ModuleStateManager>();
         let mut valid_commands = Vec::new();
         for command in data.resolve_iter(command)? {
+            if let Some(info) = command.value.module_info() {
+                if !module_state.is_enabled(ctx.handler(), info, ctx.scopes()) {
+                    continue
+                }
+            }
             if command.value.can_access(ctx).await? {
                 valid_commands.push(command.value.clone());
             }
@@ -87,25 +98,44 @@ impl CommandManager {
     /// Executes a command immediately.
     pub async fn execute(&self, ctx: &CommandCtx<impl Events>) -> Result<()> {
         if ctx.args_count() == 0 {
-            ctx.respond("Command context contains no arguments?").await?;
+            ctx.respond_msg(Message::key("commands-no-arguments")).await?;
         } else {
             let command = self.lookup_command(&ctx, ctx.arg(0).text).await?;
             match command {
-                CommandLookupResult::NoneFound => ctx.respond("No such command found.").await?,
+                CommandLookupResult::NoneFound =>
+                    ctx.respond_msg(Message::key("commands-not-found")).await?,
                 CommandLookupResult::Found(cmd) => {
-                    match Error::catch_panic_async(cmd.execute(ctx)).await {
+                    let health = ctx.handler().get_service::<HealthManager>();
+                    if let Some(msg) = health.check_disabled(&cmd) {
+                        ctx.respond_msg(msg).await?;
+                        return Ok(())
+                    }
+
+                    let module = match cmd.module_info() {
+                        Some(info) => info.name(),
+                        None => cmd.module_name(),
+                    };
+                    let span = info_span!("command", module, command = cmd.full_name());
+                    let _watch = span.in_scope(|| {
+                        let watchdog = ctx.handler().get_service::<Watchdog>();
+                        watchdog.track(format!("command '{}'", cmd.full_name()))
+                    });
+                    let result = Error::catch_panic_async(cmd.execute(ctx)).instrument(span).await;
+                    match result {
                         Ok(()) => { }
                         Err(e) => {
+                            let e = e.with_origin(module.to_string());
+                            health.record_failure(ctx.handler(), &cmd, &e);
                             // split to avoid saving a `&ErrorKind` which is !Send
                             let maybe_respond = match e.error_kind() {
-                                ErrorKind::CommandError(e) => Some(e),
+                                ErrorKind::CommandError(e) => Some(e.clone()),
                                 _ => { // TODO: Do something extensible
                                     e.report_error();
                                     None
                                 },
                             };
                             if let Some(e) = maybe_respond {
-                                ctx.respond(e).await?;
+                                ctx.respond_msg(e).await?;
                             }
                         },
                     }
@@ -115,7 +145,7 @@ impl CommandManager {
                     for cmd in cmds {
                         str.push_str(&format!("{}, ", cmd.full_name()));
                     }
-                    ctx.respond(&format!("Command is ambiguous: {}", str)).await?;
+                    ctx.respond_msg(Message::key("commands-ambiguous").arg("commands", str)).await?;
                 }
             }
         }
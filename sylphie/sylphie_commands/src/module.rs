@@ -1,7 +1,13 @@
 use async_trait::*;
 use crate::commands::*;
 use crate::ctx::*;
+use crate::dispatch::MessageDispatcher;
+use crate::health::HealthManager;
+use crate::locale::LocaleManager;
+use crate::log_levels::LogLevelManager;
 use crate::manager::*;
+use crate::module_state::ModuleStateManager;
+use crate::permissions::PermissionManager;
 use std::time::Instant;
 use sylphie_core::core::{SylphieEvents, InitEvent};
 use sylphie_core::derives::*;
@@ -20,6 +26,18 @@ pub struct CommandsModule<R: Module> {
     command_constructor: CommandImplConstructor<SylphieEvents<R>>,
     #[service] #[init_with { CommandManager::new() }]
     cmd_manager: CommandManager,
+    #[submodule] #[service]
+    module_state: ModuleStateManager,
+    #[submodule] #[service]
+    log_levels: LogLevelManager,
+    #[submodule] #[service]
+    health: HealthManager,
+    #[submodule] #[service]
+    locale: LocaleManager,
+    #[submodule] #[service]
+    dispatcher: MessageDispatcher,
+    #[submodule] #[service]
+    permissions: PermissionManager,
 }
 
 #[module_impl]
@@ -48,6 +66,7 @@ impl <R: Module> CommandsModule<R> {
     #[event_handler]
     fn setup_logger(ev: &mut SetupLoggerEvent) {
         ev.add_console_directive("sylphie_commands=debug");
+        ev.add_file_directive("sylphie_commands=debug");
     }
 }
 
@@ -68,6 +87,10 @@ impl CommandCtxImpl for TerminalContext {
         &self.raw_message
     }
 
+    fn is_operator(&self) -> bool {
+        true
+    }
+
     async fn respond<E: Events>(&self, _: &Handler<E>, msg: &str) -> Result<()> {
         info!(target: "[term]", "{}", msg);
         Ok(())
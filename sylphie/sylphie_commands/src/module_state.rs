@@ -0,0 +1,145 @@
+//! Allows modules to be enabled or disabled at runtime in particular scopes.
+
+use parking_lot::RwLock;
+use static_events::prelude_async::*;
+use std::collections::HashMap;
+use sylphie_core::core::CollectInfoEvent;
+use sylphie_core::derives::*;
+use sylphie_core::i18n::Message;
+use sylphie_core::module::{ModuleFlag, ModuleManager};
+use sylphie_core::prelude::*;
+use sylphie_database::InitDbEvent;
+use sylphie_database::kvs::KvsStore;
+use sylphie_database::serializable::SimpleSerialize;
+use sylphie_utils::scopes::Scope;
+
+/// The explicit enabled state of a module in each scope it has been set in.
+type ScopeStates = Vec<(Scope, bool)>;
+
+/// The service that tracks which modules are enabled in which scopes.
+///
+/// Modules are enabled by default. A module that is disabled also disables all of its
+/// submodules. Integral modules cannot be disabled at all.
+///
+/// Commands belonging to a disabled module cannot be looked up or executed, and its event
+/// handlers are skipped for events that occur in the scopes it is disabled in. Such events are
+/// declared with [`scoped_event!`](crate::scoped_event).
+#[derive(Module)]
+#[module(component)]
+pub struct ModuleStateManager {
+    #[module_info] info: ModuleInfo,
+    #[submodule] store: KvsStore<String, SimpleSerialize<ScopeStates>>,
+    states: RwLock<HashMap<String, ScopeStates>>,
+}
+#[module_impl]
+impl ModuleStateManager {
+    #[event_handler]
+    async fn load_states(&self, target: &Handler<impl Events>, _: &InitDbEvent) -> Result<()> {
+        let mut states = HashMap::new();
+        for module in target.get_service::<ModuleManager>().loaded_modules() {
+            if let Some(state) = self.store.get(module.name().to_string()).await? {
+                states.insert(module.name().to_string(), state.0);
+            }
+        }
+        *self.states.write() = states;
+        Ok(())
+    }
+
+    #[event_handler]
+    fn collect_info(&self, target: &Handler<impl Events>, ev: &mut CollectInfoEvent) {
+        let states = self.states.read();
+        let manager = target.get_service::<ModuleManager>();
+        let mut disabled = Vec::new();
+        for module in manager.loaded_modules() {
+            if let Some(state) = states.get(module.name()) {
+                let scopes: Vec<_> = state.iter()
+                    .filter(|(_, enabled)| !*enabled)
+                    .map(|(scope, _)| scope.to_string())
+                    .collect();
+                if !scopes.is_empty() {
+                    disabled.push(format!("    {} (in {})", module.name(), scopes.join(", ")));
+                }
+            }
+        }
+        if !disabled.is_empty() {
+            ev.add_line("Disabled modules:");
+            for line in disabled {
+                ev.add_line(line);
+            }
+        }
+    }
+
+    /// Returns whether a module cannot be disabled.
+    pub fn is_integral(&self, target: &Handler<impl Events>, module: &ModuleInfo) -> bool {
+        let flags = module.metadata().flags;
+        if flags.contains(ModuleFlag::Integral) || flags.contains(ModuleFlag::IntegralRecursive) {
+            return true
+        }
+        let manager = target.get_service::<ModuleManager>();
+        parent_names(module.name()).any(|parent| {
+            match manager.find_module(parent) {
+                Some(parent) => parent.metadata().flags.contains(ModuleFlag::IntegralRecursive),
+                None => false,
+            }
+        })
+    }
+
+    /// Returns whether a module is enabled in the given scopes.
+    ///
+    /// The scopes should be in order from most to least specific. The state set in the most
+    /// specific scope is used, and a module is only enabled if all its parents are as well.
+    pub fn is_enabled(
+        &self, target: &Handler<impl Events>, module: &ModuleInfo, scopes: &[Scope],
+    ) -> bool {
+        if self.is_integral(target, module) {
+            return true
+        }
+        let states = self.states.read();
+        if states.is_empty() {
+            return true
+        }
+        std::iter::once(module.name()).chain(parent_names(module.name())).all(|name| {
+            match states.get(name) {
+                Some(state) => scopes.iter()
+                    .find_map(|scope| state.iter().find(|(s, _)| s == scope).map(|(_, e)| *e))
+                    .unwrap_or(true),
+                None => true,
+            }
+        })
+    }
+
+    /// Sets whether a module is enabled in a given scope.
+    pub async fn set_enabled(
+        &self, target: &Handler<impl Events>, module: &ModuleInfo, scope: Scope, enabled: bool,
+    ) -> Result<()> {
+        if !enabled && self.is_integral(target, module) {
+            cmd_error!(Message::key("commands-module-integral").arg("module", module.name()));
+        }
+
+        let name = module.name().to_string();
+        let mut entry = self.store.get_mut_default(name.clone()).await?;
+        entry.0.retain(|(s, _)| *s != scope);
+        entry.0.push((scope, enabled));
+        let state = entry.0.clone();
+        entry.commit().await?;
+
+        self.states.write().insert(name, state);
+        Ok(())
+    }
+
+    /// Returns the scopes a module has been explicitly disabled in.
+    pub fn disabled_scopes(&self, module: &ModuleInfo) -> Vec<Scope> {
+        match self.states.read().get(module.name()) {
+            Some(state) => state.iter()
+                .filter(|(_, enabled)| !*enabled)
+                .map(|(scope, _)| scope.clone())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Returns the names of every parent of a module, from the closest to the furthest.
+fn parent_names(name: &str) -> impl Iterator<Item = &str> {
+    name.char_indices().rev().filter(|(_, c)| *c == '.').map(move |(i, _)| &name[..i])
+}
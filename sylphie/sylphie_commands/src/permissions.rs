@@ -0,0 +1,143 @@
+//! Controls which users may use commands, with permissions granted in particular scopes.
+
+use crate::manager::CommandManager;
+use parking_lot::RwLock;
+use static_events::prelude_async::*;
+use std::collections::{BTreeSet, HashMap};
+use sylphie_core::core::InitEvent;
+use sylphie_core::derives::*;
+use sylphie_core::prelude::*;
+use sylphie_database::kvs::KvsStore;
+use sylphie_database::serializable::SimpleSerialize;
+use sylphie_utils::scopes::Scope;
+
+/// The explicit state of a permission in each scope it has been set in.
+type ScopeGrants = Vec<(Scope, bool)>;
+
+/// Whether a permission is granted in scopes it has not been explicitly set in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum PermissionPolicy {
+    /// The permission is granted unless it has been explicitly denied.
+    DefaultAllow,
+    /// The permission is denied unless it has been explicitly granted.
+    DefaultDeny,
+}
+impl Default for PermissionPolicy {
+    fn default() -> Self {
+        PermissionPolicy::DefaultDeny
+    }
+}
+
+/// The event used to register the policy of each permission.
+///
+/// Permissions that are not registered are denied by default.
+#[derive(Debug, Default)]
+pub struct RegisterPermissionsEvent {
+    policies: HashMap<String, PermissionPolicy>,
+}
+self_event!(RegisterPermissionsEvent);
+impl RegisterPermissionsEvent {
+    /// Registers the policy used for a permission.
+    pub fn register_permission(&mut self, permission: &str, policy: PermissionPolicy) {
+        self.policies.insert(permission.to_string(), policy);
+    }
+}
+
+/// The service that tracks which permissions are granted in which scopes.
+///
+/// Commands may require a permission, such as with `#[command(permission = "admin")]`. These
+/// commands cannot be looked up or executed unless the permission is granted in the scopes
+/// the command is used in. Commands used from contexts such as the terminal, where
+/// [`CommandCtxImpl::is_operator`](crate::ctx::CommandCtxImpl::is_operator) is true, may use
+/// every permission.
+#[derive(Module)]
+#[module(component)]
+pub struct PermissionManager {
+    #[module_info] info: ModuleInfo,
+    #[submodule] store: KvsStore<String, SimpleSerialize<ScopeGrants>>,
+    policies: RwLock<HashMap<String, PermissionPolicy>>,
+    grants: RwLock<HashMap<String, ScopeGrants>>,
+}
+#[module_impl]
+impl PermissionManager {
+    #[event_handler]
+    fn load_policies(&self, target: &Handler<impl Events>, _: &InitEvent) {
+        let ev = target.dispatch_sync(RegisterPermissionsEvent::default());
+        *self.policies.write() = ev.policies;
+    }
+
+    /// Returns the policy used for a permission.
+    pub fn policy(&self, permission: &str) -> PermissionPolicy {
+        self.policies.read().get(permission).cloned().unwrap_or_default()
+    }
+
+    /// Returns the name of every permission that has been registered or is required by a
+    /// command, in sorted order.
+    pub fn known_permissions(&self, target: &Handler<impl Events>) -> Vec<String> {
+        let mut permissions: BTreeSet<_> = self.policies.read().keys().cloned().collect();
+        for command in &*target.get_service::<CommandManager>().command_list() {
+            if let Some(permission) = &command.value.info().permission {
+                permissions.insert(permission.to_string());
+            }
+        }
+        permissions.into_iter().collect()
+    }
+
+    /// Returns the scopes a permission has been explicitly granted or denied in.
+    pub async fn grants(&self, permission: &str) -> Result<Vec<(Scope, bool)>> {
+        let cached = self.grants.read().get(permission).cloned();
+        if let Some(grants) = cached {
+            return Ok(grants)
+        }
+        let stored = self.store.get(permission.to_string()).await?;
+        let grants = stored.map_or_else(Vec::new, |x| x.0);
+        // `update` may have cached a newer value while the store was being read.
+        let mut cache = self.grants.write();
+        Ok(cache.entry(permission.to_string()).or_insert(grants).clone())
+    }
+
+    /// Returns whether a permission is granted in the given scopes.
+    ///
+    /// The scopes should be in order from most to least specific. The state set in the most
+    /// specific scope is used, falling back to the permission's policy.
+    pub async fn has_permission(&self, permission: &str, scopes: &[Scope]) -> Result<bool> {
+        let grants = self.grants(permission).await?;
+        let explicit = scopes.iter()
+            .find_map(|scope| grants.iter().find(|(s, _)| s == scope).map(|(_, g)| *g));
+        Ok(explicit.unwrap_or_else(|| self.policy(permission) == PermissionPolicy::DefaultAllow))
+    }
+
+    /// Explicitly grants or denies a permission in a given scope.
+    pub async fn set_permission(
+        &self, permission: &str, scope: Scope, granted: bool,
+    ) -> Result<()> {
+        self.update(permission, |grants| {
+            grants.retain(|(s, _)| *s != scope);
+            grants.push((scope, granted));
+        }).await
+    }
+
+    /// Removes the explicit state of a permission in a given scope, so the state set in less
+    /// specific scopes or the permission's policy is used instead.
+    ///
+    /// Returns whether the permission had been set in the scope.
+    pub async fn reset_permission(&self, permission: &str, scope: &Scope) -> Result<bool> {
+        let mut removed = false;
+        self.update(permission, |grants| {
+            let len = grants.len();
+            grants.retain(|(s, _)| s != scope);
+            removed = grants.len() != len;
+        }).await?;
+        Ok(removed)
+    }
+
+    async fn update(&self, permission: &str, func: impl FnOnce(&mut ScopeGrants)) -> Result<()> {
+        let mut entry = self.store.get_mut_default(permission.to_string()).await?;
+        func(&mut entry.0);
+        let grants = entry.0.clone();
+        entry.commit().await?;
+
+        self.grants.write().insert(permission.to_string(), grants);
+        Ok(())
+    }
+}
@@ -9,6 +9,10 @@ pub struct ArgParsingOptions {
     /// Whether to parse the input as markdown.
     #[setters(bool)]
     pub parse_markdown: bool,
+    /// Whether arguments such as `--flag`, `--key=value` or `-abc` are kept as positional
+    /// arguments, rather than being parsed as flags.
+    #[setters(bool)]
+    pub ignore_flags: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -74,6 +78,67 @@ impl ArgsSpan {
     }
 }
 
+#[derive(Clone, Debug)]
+struct FlagSpan {
+    name: String,
+    value: Option<ArgsType>,
+    source_span: (usize, usize),
+}
+
+fn is_flag_name(name: &str) -> bool {
+    name.starts_with(char::is_alphabetic) &&
+        name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Parses an argument as a list of flags, returning `None` if it is a positional argument.
+fn parse_flag(source: &str, arg: &ArgsSpan) -> Option<Vec<FlagSpan>> {
+    let raw = &source[arg.source_span.0..arg.source_span.1];
+    let text = arg.tp.as_str(source);
+    if !raw.starts_with('-') {
+        None
+    } else if text.starts_with("--") {
+        let (name, value_offset) = match text.find('=') {
+            Some(idx) => (&text[2..idx], Some(idx + 1)),
+            None => (&text[2..], None),
+        };
+        if !is_flag_name(name) {
+            return None
+        }
+        let value = value_offset.map(|offset| match &arg.tp {
+            ArgsType::Span(start, end) => ArgsType::Span(start + offset, *end),
+            _ => ArgsType::Inline(text[offset..].to_string()),
+        });
+        Some(vec![FlagSpan { name: name.to_string(), value, source_span: arg.source_span }])
+    } else if text.len() > 1 && text[1..].chars().all(char::is_alphabetic) {
+        Some(text[1..].chars().map(|ch| FlagSpan {
+            name: ch.to_string(),
+            value: None,
+            source_span: arg.source_span,
+        }).collect())
+    } else {
+        None
+    }
+}
+
+/// Separates the flags from the positional arguments. Every argument after a `--` argument is
+/// treated as a positional argument.
+fn extract_flags(source: &str, args: Vec<ArgsSpan>) -> (Vec<ArgsSpan>, Vec<FlagSpan>) {
+    let mut positional = Vec::new();
+    let mut flags = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if &source[arg.source_span.0..arg.source_span.1] == "--" {
+            positional.extend(args);
+            break
+        }
+        match parse_flag(source, &arg) {
+            Some(parsed) => flags.extend(parsed),
+            None => positional.push(arg),
+        }
+    }
+    (positional, flags)
+}
+
 #[derive(Clone, Debug)]
 struct ParserTokenCtx<'a> {
     /// The source string.
@@ -167,6 +232,7 @@ impl <'a> ParserTokenCtx<'a> {
 /// Note that this only stores indicies.
 pub struct Args {
     args_spans: Vec<ArgsSpan>,
+    flags: Vec<FlagSpan>,
 }
 impl Args {
     pub fn parse(options: ArgParsingOptions, source: &str) -> Args {
@@ -345,9 +411,12 @@ impl Args {
             break 'main;
         }
 
-        Args {
-            args_spans: ctx.args,
-        }
+        let (args_spans, flags) = if options.ignore_flags {
+            (ctx.args, Vec::new())
+        } else {
+            extract_flags(source, ctx.args)
+        };
+        Args { args_spans, flags }
     }
 
     pub fn len(&self) -> usize {
@@ -360,6 +429,20 @@ impl Args {
     pub fn source_span(&self, i: usize) -> (usize, usize) {
         self.args_spans[i].source_span
     }
+
+    pub fn flags_len(&self) -> usize {
+        self.flags.len()
+    }
+
+    pub fn flag_name(&self, i: usize) -> &str {
+        &self.flags[i].name
+    }
+    pub fn flag_value<'a: 'c, 'b: 'c, 'c>(&'a self, source: &'b str, i: usize) -> Option<&'c str> {
+        self.flags[i].value.as_ref().map(|x| x.as_str(source))
+    }
+    pub fn flag_source_span(&self, i: usize) -> (usize, usize) {
+        self.flags[i].source_span
+    }
 }
 
 #[cfg(test)]
@@ -443,6 +526,44 @@ mod tests {
         check_parser(options, r#""abc `def" ghi`"# , &[r"abc `def", r"ghi`"]);
     }
 
+    fn check_flags(
+        options: ArgParsingOptions, source: &str,
+        expected_args: &[&str], expected_flags: &[(&str, Option<&str>)],
+    ) {
+        let parsed = Args::parse(options, source);
+
+        let mut args = Vec::new();
+        for i in 0..parsed.len() {
+            args.push(parsed.arg(source, i));
+        }
+        let mut flags = Vec::new();
+        for i in 0..parsed.flags_len() {
+            flags.push((parsed.flag_name(i), parsed.flag_value(source, i)));
+        }
+        assert_eq!(args, expected_args);
+        assert_eq!(flags, expected_flags);
+    }
+
+    #[test]
+    fn flags_test() {
+        let options = ArgParsingOptions::default();
+        check_flags(options, "a --force b", &["a", "b"], &[("force", None)]);
+        check_flags(options, "--key=value a", &["a"], &[("key", Some("value"))]);
+        check_flags(options, r#"--key="a b" c"#, &["c"], &[("key", Some("a b"))]);
+        check_flags(options, "--key= a", &["a"], &[("key", Some(""))]);
+        check_flags(options, "-ab c", &["c"], &[("a", None), ("b", None)]);
+        check_flags(options, "-5 - --", &["-5", "-"], &[]);
+        check_flags(options, "a -- --force -f", &["a", "--force", "-f"], &[]);
+        check_flags(options, r#""--force" \-f"#, &["--force", r"\-f"], &[]);
+        check_flags(options, "--=a ---b", &["--=a", "---b"], &[]);
+    }
+
+    #[test]
+    fn ignore_flags_test() {
+        let options = ArgParsingOptions::default().ignore_flags();
+        check_flags(options, "a --force -ab --", &["a", "--force", "-ab", "--"], &[]);
+    }
+
     #[test]
     fn source_span() {
         let options = ArgParsingOptions::default().parse_markdown();
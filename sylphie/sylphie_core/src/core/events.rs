@@ -1,55 +1,68 @@
-use crate::core::{ShutdownStartedEvent, SylphieCoreHandlerExt};
+use crate::core::{
+    BuiltinCommandEvent, CollectInfoEvent, RegisterBuiltinCommandsEvent, ShutdownEvent,
+    ShutdownStartedEvent, SylphieCoreHandlerExt,
+};
 use crate::interface::{TerminalCommandEvent, Interface, SetupLoggerEvent};
 use crate::module::{Module, ModuleManager};
+use crate::timer::Timer;
+use crate::watchdog::Watchdog;
 use static_events::prelude_async::*;
 use std::marker::PhantomData;
+use std::sync::atomic::AtomicBool;
 
 #[derive(Events)]
 pub struct SylphieEventsImpl<R: Module>(pub PhantomData<R>);
 
 #[events_impl]
 impl <R: Module> SylphieEventsImpl<R> {
-    #[event_handler(EvBeforeEvent)]
-    fn builtin_commands(
-        &self, target: &Handler<impl Events>, command: &TerminalCommandEvent,
-    ) -> EventResult {
-        match command.0.to_ascii_lowercase().as_str().trim() {
-            ".help" => {
+    #[event_handler]
+    fn register_builtin_commands(ev: &mut RegisterBuiltinCommandsEvent) {
+        ev.register(".help", "Shows this help message.");
+        ev.register(".info", "Prints information about the bot.");
+        ev.register(".shutdown", "Shuts down the bot.");
+        ev.register(".abort!!", "Forcefully shuts down the bot.");
+    }
+
+    #[event_handler]
+    fn builtin_commands(&self, target: &Handler<impl Events>, command: &BuiltinCommandEvent) {
+        match command.name() {
+            "help" => {
+                let mut commands = target.dispatch_sync(RegisterBuiltinCommandsEvent::default())
+                    .commands;
+                commands.sort_by(|a, b| a.usage.cmp(&b.usage));
                 info!(target: "[term]", "Built-in commands:");
-                info!(target: "[term]", ".help - Shows this help message.");
-                info!(target: "[term]", ".info - Prints information about the bot.");
-                info!(target: "[term]", ".shutdown - Shuts down the bot.");
-                info!(target: "[term]", ".abort!! - Forcefully shuts down the bot.");
+                for command in commands {
+                    info!(target: "[term]", "{} - {}", command.usage, command.help);
+                }
             }
-            ".info" => {
+            "info" => {
                 info!(target: "[term]", "Loaded modules:");
                 for module in target.get_service::<ModuleManager>().loaded_modules() {
                     info!(target: "[term]", "    {}", module.name());
                 }
-                for info_line in crate::interface::get_info_string().trim().split('\n') {
+                let extra_info = target.dispatch_sync(CollectInfoEvent::default());
+                for line in extra_info.lines {
+                    info!(target: "[term]", "{}", line);
+                }
+                let info = target.get_service::<Interface>().info_string();
+                for info_line in info.trim().split('\n') {
                     info!(target: "[term]", "{}", info_line);
                 }
             }
-            ".shutdown" => target.shutdown_bot(),
-            ".abort!!" => {
+            "shutdown" => target.shutdown_bot(),
+            "abort!!" => {
                 eprintln!("(abort)");
                 ::std::process::abort()
             }
-            x if x.starts_with(".abort") => {
+            x if x.starts_with("abort") => {
                 info!(
                     target: "[term]",
                     "Please use '.abort!!' if you really mean to forcefully stop the bot.",
                 );
             }
-            x if x.starts_with('.') => {
-                error!(
-                    target: "[term]",
-                    "Unknown built-in command. Use '.help' for more information.",
-                );
-            }
-            _ => return EvOk
+            _ => return
         }
-        EvCancel
+        command.set_handled();
     }
 
     #[event_handler]
@@ -57,8 +70,38 @@ impl <R: Module> SylphieEventsImpl<R> {
         target.get_service::<Interface>().shutdown();
     }
 
+    #[event_handler(EvInit)]
+    fn cancel_timers(&self, target: &Handler<impl Events>, _: &ShutdownEvent) {
+        target.get_service::<Timer>().shutdown();
+    }
+
     #[event_handler]
     fn setup_logger(ev: &mut SetupLoggerEvent) {
         ev.add_console_directive("sylphie_core=debug");
+        ev.add_file_directive("sylphie_core=debug");
+    }
+}
+
+/// Runs a line entered in the terminal, either as a built-in command if it starts with a
+/// period, or as a [`TerminalCommandEvent`] otherwise.
+pub(crate) async fn dispatch_terminal_command(target: &Handler<impl Events>, line: String) {
+    let watchdog = target.get_service::<Watchdog>();
+    let _watch = watchdog.track(format!("terminal command '{}'", line.trim()));
+    let trimmed = line.trim();
+    if trimmed.starts_with('.') {
+        let (name, args) = match trimmed.find(char::is_whitespace) {
+            Some(i) => (&trimmed[1..i], trimmed[i..].trim()),
+            None => (&trimmed[1..], ""),
+        };
+        let command = target.dispatch_async(BuiltinCommandEvent {
+            name: name.to_ascii_lowercase(),
+            args: args.to_string(),
+            is_handled: AtomicBool::new(false),
+        }).await;
+        if !command.is_handled() {
+            error!(target: "[term]", "Unknown built-in command. Use '.help' for more information.");
+        }
+    } else {
+        target.dispatch_async(TerminalCommandEvent(line)).await;
     }
-}
\ No newline at end of file
+}
@@ -1,19 +1,24 @@
 use crate::errors::*;
-use crate::global_instance::*;
 use crate::interface::*;
 use crate::module::{Module, ModuleManager};
+use crate::timer::Timer;
+use crate::watchdog::Watchdog;
 use fs2::*;
-use lazy_static::*;
+use futures::future::AbortHandle;
 use static_events::prelude_async::*;
 use std::env;
 use std::fs::{self, File, OpenOptions};
+use std::future::Future;
 use std::path::{Path, PathBuf};
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 mod events;
 
+pub(crate) use events::dispatch_terminal_command;
+
 fn check_lock(path: impl AsRef<Path>) -> Result<File> {
     let mut options = OpenOptions::new();
     options.create(true).read(true).write(true);
@@ -84,6 +89,83 @@ simple_event!(ShutdownEvent);
 struct ShutdownStartedEvent;
 simple_event!(ShutdownStartedEvent);
 
+/// Dispatched when the `.info` terminal command is used, to collect additional lines of
+/// information about the bot's state.
+///
+/// This event is dispatched synchronously.
+#[derive(Default)]
+pub struct CollectInfoEvent {
+    lines: Vec<String>,
+}
+self_event!(CollectInfoEvent);
+impl CollectInfoEvent {
+    /// Adds a line of information to be printed.
+    pub fn add_line(&mut self, line: impl Into<String>) {
+        self.lines.push(line.into());
+    }
+}
+
+/// Information about a built-in command, as shown by `.help`.
+#[derive(Clone, Debug)]
+pub struct BuiltinCommandInfo {
+    /// How the command is used, such as `.loglevel <directive>`.
+    pub usage: String,
+    /// A short description of what the command does.
+    pub help: String,
+}
+
+/// Dispatched to collect the built-in commands that can be used from the terminal, so they can
+/// be listed by `.help`.
+///
+/// This event is dispatched synchronously.
+#[derive(Default)]
+pub struct RegisterBuiltinCommandsEvent {
+    commands: Vec<BuiltinCommandInfo>,
+}
+self_event!(RegisterBuiltinCommandsEvent);
+impl RegisterBuiltinCommandsEvent {
+    /// Registers a built-in command.
+    ///
+    /// The usage string should start with the command's name, including the leading period.
+    pub fn register(&mut self, usage: impl Into<String>, help: impl Into<String>) {
+        self.commands.push(BuiltinCommandInfo { usage: usage.into(), help: help.into() });
+    }
+}
+
+/// Dispatched when a built-in command (a line starting with a period) is entered in the
+/// terminal.
+///
+/// Modules handling a command should call [`BuiltinCommandEvent::set_handled`], and should also
+/// register it with [`RegisterBuiltinCommandsEvent`]. If no module handles a command, an error
+/// is shown.
+pub struct BuiltinCommandEvent {
+    name: String,
+    args: String,
+    is_handled: AtomicBool,
+}
+self_event!(BuiltinCommandEvent);
+impl BuiltinCommandEvent {
+    /// Returns the name of the command, in lowercase and without the leading period.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the rest of the line following the command's name.
+    pub fn args(&self) -> &str {
+        &self.args
+    }
+
+    /// Returns whether the command has been handled.
+    pub fn is_handled(&self) -> bool {
+        self.is_handled.load(Ordering::Relaxed)
+    }
+
+    /// Marks the command as having been handled.
+    pub fn set_handled(&self) {
+        self.is_handled.store(true, Ordering::Relaxed);
+    }
+}
+
 /// The [`Events`] implementation used for a particular [`SylphieCore`].
 #[derive(Events)]
 pub struct SylphieEvents<R: Module> {
@@ -92,10 +174,8 @@ pub struct SylphieEvents<R: Module> {
     #[service] module_manager: ModuleManager,
     #[service] interface: Interface,
     #[service] bot_info: BotInfo,
-}
-
-lazy_static! {
-    static ref SYLPHIE_RUNNING_GUARD: GlobalInstance<()> = GlobalInstance::new();
+    #[service] timer: Timer,
+    #[service] watchdog: Watchdog,
 }
 
 /// Stores information related to the bot.
@@ -105,6 +185,8 @@ lazy_static! {
 pub struct BotInfo {
     bot_name: String,
     root_path: PathBuf,
+    data_path: PathBuf,
+    log_path: PathBuf,
 }
 impl BotInfo {
     /// Returns the name of the bot.
@@ -116,24 +198,208 @@ impl BotInfo {
     pub fn root_path(&self) -> &Path {
         &self.root_path
     }
+
+    /// Returns the path where the bot's databases are stored.
+    pub fn data_path(&self) -> &Path {
+        &self.data_path
+    }
+
+    /// Returns the path where the bot's log files and error reports are stored.
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    /// Returns the path of the bot's admin socket.
+    pub fn admin_socket_path(&self) -> PathBuf {
+        self.root_path.join("admin.sock")
+    }
+}
+
+/// A builder for [`SylphieCore`], allowing the bot's paths and runtime to be configured.
+pub struct SylphieCoreBuilder<R: Module> {
+    bot_name: String,
+    root_path: Option<PathBuf>,
+    data_dir: PathBuf,
+    log_dir: PathBuf,
+    worker_threads: Option<usize>,
+    use_lock_file: bool,
+    use_admin_socket: bool,
+    interface_mode: InterfaceMode,
+    shutdown_timeout: Duration,
+    watchdog_threshold: Duration,
+    report_settings: ReportSettings,
+    phantom: PhantomData<R>,
+}
+impl <R: Module> SylphieCoreBuilder<R> {
+    /// Sets the path where the bot's state is stored.
+    ///
+    /// By default, this is the `run` directory in the workspace when run through Cargo, or in
+    /// the directory containing the executable otherwise.
+    pub fn root_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.root_path = Some(path.into());
+        self
+    }
+
+    /// Sets the directory where databases are stored, relative to the root path. This defaults
+    /// to `db`.
+    pub fn data_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = dir.into();
+        self
+    }
+
+    /// Sets the directory where log files and error reports are stored, relative to the root
+    /// path. This defaults to `logs`.
+    pub fn log_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.log_dir = dir.into();
+        self
+    }
+
+    /// Sets the number of worker threads used by the async runtime. This defaults to the
+    /// number of CPU cores.
+    pub fn worker_threads(mut self, count: usize) -> Self {
+        self.worker_threads = Some(count);
+        self
+    }
+
+    /// Sets whether a `.lock` file is taken in the root path, preventing multiple instances of
+    /// the bot from using the same state at once. This defaults to `true`.
+    pub fn lock_file(mut self, use_lock_file: bool) -> Self {
+        self.use_lock_file = use_lock_file;
+        self
+    }
+
+    /// Sets whether an `admin.sock` Unix domain socket is created in the root path while the
+    /// bot is running. This defaults to `true`, and has no effect on other platforms.
+    ///
+    /// Only the user the bot runs as may connect to the socket. Each line sent to it is run as
+    /// a terminal command, and the messages logged by that command are sent back. Use
+    /// [`SylphieCore::connect_admin`] to connect to it.
+    pub fn admin_socket(mut self, use_admin_socket: bool) -> Self {
+        self.use_admin_socket = use_admin_socket;
+        self
+    }
+
+    /// Sets how the bot interacts with the terminal. This defaults to
+    /// [`InterfaceMode::Terminal`].
+    ///
+    /// Use [`InterfaceMode::Headless`] when running without a TTY, such as under a service
+    /// manager or in a container.
+    pub fn interface_mode(mut self, mode: InterfaceMode) -> Self {
+        self.interface_mode = mode;
+        self
+    }
+
+    /// Sets how long to wait for background tasks to stop after the bot is shut down. Once this
+    /// time has passed, an error report is written and the bot exits anyway.
+    ///
+    /// This defaults to 30 seconds.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a command, terminal command or other operation tracked by the
+    /// [`Watchdog`] may run for before it is considered stuck, and an error report is written
+    /// listing it.
+    ///
+    /// This defaults to 5 minutes. Setting it to zero disables the watchdog.
+    pub fn watchdog_threshold(mut self, threshold: Duration) -> Self {
+        self.watchdog_threshold = threshold;
+        self
+    }
+
+    /// Sets the URL users are asked to report bugs at when an error report is written. By
+    /// default, no URL is shown.
+    pub fn report_url(mut self, url: impl Into<String>) -> Self {
+        self.report_settings.report_url = Some(url.into());
+        self
+    }
+
+    /// Sets how long an error is remembered for after a report is written for it. Errors that
+    /// occur in the same place again during this time do not produce a new report.
+    ///
+    /// This defaults to 10 minutes. Setting it to zero disables deduplication.
+    pub fn report_dedup_window(mut self, window: Duration) -> Self {
+        self.report_settings.dedup_window = window;
+        self
+    }
+
+    /// Sets the maximum number of error reports written in a given period of time. Errors
+    /// reported beyond this limit are logged, but no report is written for them.
+    ///
+    /// This defaults to 10 reports per minute.
+    pub fn report_rate_limit(mut self, count: u32, period: Duration) -> Self {
+        self.report_settings.rate_limit = count;
+        self.report_settings.rate_limit_period = period;
+        self
+    }
+
+    /// Creates the bot core.
+    pub fn build(self) -> SylphieCore<R> {
+        let root_path = self.root_path.unwrap_or_else(|| {
+            let mut root_path = get_root_path();
+            root_path.push("run");
+            root_path
+        });
+        SylphieCore {
+            info: BotInfo {
+                bot_name: self.bot_name,
+                data_path: root_path.join(self.data_dir),
+                log_path: root_path.join(self.log_dir),
+                root_path,
+            },
+            worker_threads: self.worker_threads,
+            use_lock_file: self.use_lock_file,
+            use_admin_socket: self.use_admin_socket,
+            interface_mode: self.interface_mode,
+            shutdown_timeout: self.shutdown_timeout,
+            watchdog_threshold: self.watchdog_threshold,
+            report_settings: self.report_settings,
+            phantom: PhantomData,
+        }
+    }
 }
 
 pub struct SylphieCore<R: Module> {
     info: BotInfo,
+    worker_threads: Option<usize>,
+    use_lock_file: bool,
+    use_admin_socket: bool,
+    interface_mode: InterfaceMode,
+    shutdown_timeout: Duration,
+    watchdog_threshold: Duration,
+    report_settings: ReportSettings,
     phantom: PhantomData<R>,
 }
 impl <R: Module> SylphieCore<R> {
+    /// Creates a bot core with the default options.
     pub fn new(bot_name: impl Into<String>) -> Self {
-        let mut root_path = get_root_path();
-        root_path.push("run");
-        SylphieCore {
-            info: BotInfo {
-                bot_name: bot_name.into(),
-                root_path,
+        Self::builder(bot_name).build()
+    }
+
+    /// Creates a builder for a bot core, allowing its options to be configured.
+    pub fn builder(bot_name: impl Into<String>) -> SylphieCoreBuilder<R> {
+        SylphieCoreBuilder {
+            bot_name: bot_name.into(),
+            root_path: None,
+            data_dir: PathBuf::from("db"),
+            log_dir: PathBuf::from("logs"),
+            worker_threads: None,
+            use_lock_file: true,
+            use_admin_socket: true,
+            interface_mode: InterfaceMode::Terminal,
+            shutdown_timeout: Duration::from_secs(30),
+            watchdog_threshold: Duration::from_secs(5 * 60),
+            report_settings: ReportSettings {
+                report_url: None,
+                dedup_window: Duration::from_secs(10 * 60),
+                rate_limit: 10,
+                rate_limit_period: Duration::from_secs(60),
             },
             phantom: PhantomData,
         }
     }
+
     fn lock(&mut self) -> Result<File> {
         let mut lock_path = self.info.root_path.clone();
         if !lock_path.is_dir() {
@@ -151,38 +417,94 @@ impl <R: Module> SylphieCore<R> {
     ///
     /// This sets the panic hook to allow for better error reporting.
     ///
-    /// # Panics
+    /// Several bot cores may run in the same process, as long as they use different root paths.
+    /// Errors are reported to the bot core whose thread they occur on.
+    pub fn start(self) -> Result<()> {
+        self.run_core(true, |handler, interface| interface.start(handler))
+    }
+
+    /// Starts the bot core, runs the given terminal commands in order, then shuts it down.
     ///
-    /// Only one bot core may be started at one time. Any cores started while another core is
-    /// running
-    pub fn start(mut self) -> Result<()> {
-        // acquire the per-process lock
-        let _guard = SYLPHIE_RUNNING_GUARD.set_instance(());
+    /// This is otherwise identical to [`SylphieCore::start`].
+    pub fn exec(self, commands: Vec<String>) -> Result<()> {
+        self.run_core(true, |handler, interface| interface.with_logger(handler, || {
+            let runtime = tokio::runtime::Handle::current();
+            for command in commands {
+                runtime.block_on(dispatch_terminal_command(handler, command));
+            }
+            Ok(())
+        }))
+    }
+
+    /// Connects to the admin socket of an instance of this bot that is already running, and
+    /// runs the given commands on it, printing their output.
+    ///
+    /// If no commands are given, commands are read from standard input until it is closed.
+    pub fn connect_admin(self, commands: Vec<String>) -> Result<()> {
+        crate::interface::run_admin_client(&self.info.admin_socket_path(), commands)
+    }
 
+    /// Initializes the bot core for maintenance tasks, runs the given function, then shuts it
+    /// down.
+    ///
+    /// Only [`EarlyInitEvent`] is dispatched, so the database is initialized and migrated, but
+    /// no connections are made, no commands are loaded and no scheduled jobs are run. The
+    /// function is run inside the bot's async runtime.
+    pub fn run_maintenance<T>(
+        self, func: impl FnOnce(&Handler<SylphieEvents<R>>) -> Result<T>,
+    ) -> Result<T> {
+        self.run_core(false, |handler, _| func(handler))
+    }
+
+    /// Starts the bot core without a terminal, and returns a handle that can be used to
+    /// interact with it.
+    ///
+    /// The bot's logger is active on the current thread until the returned core is shut down.
+    /// This is meant for embedding the bot in other programs, or for testing.
+    pub fn boot(self) -> Result<BootedCore<R>> {
+        let mut core = self.boot_inner(true)?;
+        let logger = core.interface.activate_logger(&core.handler)?;
+        core.logger = Some(logger);
+        Ok(core)
+    }
+
+    fn boot_inner(mut self, full_init: bool) -> Result<BootedCore<R>> {
         // initialize early logging and related processes
         early_init();
 
         // acquire the database lock
-        let _lock = self.lock()?;
+        let lock = if self.use_lock_file { Some(self.lock()?) } else { None };
+
+        // initialize the interface system
+        let interface_info = InterfaceInfo {
+            bot_name: self.info.bot_name.clone(),
+            log_path: self.info.log_path.clone(),
+            mode: self.interface_mode,
+            admin_socket: if self.use_admin_socket {
+                Some(self.info.admin_socket_path())
+            } else {
+                None
+            },
+            report_settings: self.report_settings.clone(),
+        };
+        let interface = Interface::new(interface_info)
+            .internal_err(|| "Could not initialize user interface.")?;
+        let _error_ctx = interface.enter_error_ctx();
 
         // initializes the tokio runtime
-        let runtime = tokio::runtime::Builder::new()
-            .threaded_scheduler()
-            .enable_all()
-            .build()?;
-        runtime.enter(move || -> Result<()> {
+        let mut runtime = tokio::runtime::Builder::new();
+        runtime.threaded_scheduler().enable_all();
+        if let Some(threads) = self.worker_threads {
+            runtime.core_threads(threads);
+        }
+        let thread_interface = interface.clone();
+        runtime.on_thread_start(move || thread_interface.attach_thread());
+        let runtime = runtime.build()?;
+        let handler = runtime.enter(|| -> Result<_> {
             let runtime = tokio::runtime::Handle::current();
 
-            // initialize the interface system
-            let interface_info = InterfaceInfo {
-                bot_name: self.info.bot_name.clone(),
-                root_path: self.info.root_path.clone(),
-            };
-            let interface = Interface::new(interface_info)
-                .internal_err(|| "Could not initialize user interface.")?;
-
             // initialize the module tree and events dispatch
-            let (module_manager, root_module) = ModuleManager::init::<R>();
+            let (module_manager, root_module) = ModuleManager::init::<R>()?;
             interface.set_loaded_crates(module_manager.loaded_crates_list());
             let handler = Handler::new(SylphieEvents {
                 root_module,
@@ -190,17 +512,142 @@ impl <R: Module> SylphieCore<R> {
                 module_manager,
                 interface: interface.clone(),
                 bot_info: self.info.clone(),
+                timer: Timer::new(),
+                watchdog: Watchdog::new(self.watchdog_threshold),
             });
 
+            handler.get_service::<Watchdog>().start(&interface);
+
             // start the actual bot itself
-            handler.dispatch_sync(EarlyInitEvent(()))?;
-            runtime.block_on(handler.dispatch_async(InitEvent(())))?;
-            interface.start(&handler)?;
+            let result = (|| -> Result<()> {
+                handler.dispatch_sync(EarlyInitEvent(()))?;
+                if full_init {
+                    let watchdog = handler.get_service::<Watchdog>();
+                    let init = handler.dispatch_async(InitEvent(()));
+                    runtime.block_on(watchdog.watch("InitEvent dispatch", init))?;
+                }
+                Ok(())
+            })();
+            if let Err(e) = result {
+                handler.get_service::<Watchdog>().shutdown();
+                return Err(e)
+            }
+
+            // reports are only forwarded once startup has finished
+            interface.set_report_target(&handler);
+            Ok(handler)
+        })?;
+
+        Ok(BootedCore {
+            handler,
+            interface,
+            logger: None,
+            shutdown_timeout: self.shutdown_timeout,
+            is_shutdown: false,
+            runtime,
+            _lock: lock,
+        })
+    }
+
+    fn run_core<T>(
+        self, full_init: bool,
+        body: impl FnOnce(&Handler<SylphieEvents<R>>, &Interface) -> Result<T>,
+    ) -> Result<T> {
+        let core = self.boot_inner(full_init)?;
+        #[cfg(unix)]
+        let signals = if full_init {
+            let (abort, registration) = AbortHandle::new_pair();
+            let target = core.handler.clone();
+            core.runtime.spawn(futures::future::Abortable::new(async move {
+                if let Err(e) = handle_signals(target).await {
+                    e.report_error();
+                }
+            }, registration));
+            Some(abort)
+        } else {
+            None
+        };
+        #[cfg(not(unix))]
+        let signals: Option<AbortHandle> = None;
+
+        let result = core.enter(|| body(&core.handler, &core.interface));
+        // the signal handler keeps a reference to the event handler, so it must be stopped
+        // before the bot can finish shutting down
+        if let Some(signals) = signals {
+            signals.abort();
+        }
+        core.shutdown();
+        result
+    }
+}
+
+/// A bot core that has been started with [`SylphieCore::boot`].
+///
+/// The bot is shut down when this is dropped.
+pub struct BootedCore<R: Module> {
+    handler: Handler<SylphieEvents<R>>,
+    interface: Interface,
+    logger: Option<LoggerGuard>,
+    shutdown_timeout: Duration,
+    is_shutdown: bool,
+    runtime: tokio::runtime::Runtime,
+    _lock: Option<File>,
+}
+impl <R: Module> BootedCore<R> {
+    /// Returns the bot's event handler.
+    pub fn handler(&self) -> &Handler<SylphieEvents<R>> {
+        &self.handler
+    }
+
+    /// Returns the bot's interface service.
+    pub fn interface(&self) -> &Interface {
+        &self.interface
+    }
+
+    /// Runs a function in the context of the bot's async runtime.
+    pub fn enter<T>(&self, func: impl FnOnce() -> T) -> T {
+        self.runtime.enter(func)
+    }
+
+    /// Runs a future to completion on the bot's async runtime, blocking the current thread.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.enter(|| tokio::runtime::Handle::current().block_on(future))
+    }
+
+    /// Shuts down the bot, waiting for its background tasks to stop.
+    pub fn shutdown(mut self) {
+        self.shutdown_inner();
+    }
+
+    fn shutdown_inner(&mut self) {
+        if self.is_shutdown {
+            return
+        }
+        self.is_shutdown = true;
+
+        let handler = &self.handler;
+        let interface = &self.interface;
+        let shutdown_timeout = self.shutdown_timeout;
+        self.interface.shutdown();
+        self.runtime.enter(|| {
+            let _error_ctx = interface.enter_error_ctx();
+            let runtime = tokio::runtime::Handle::current();
             runtime.block_on(handler.dispatch_async(ShutdownEvent(())));
+            handler.get_service::<Watchdog>().shutdown();
+            interface.clear_report_target();
 
             // wait for shutdown
+            let shutdown_started = Instant::now();
             let mut ct = 0;
             while handler.refcount() > 1 {
+                if shutdown_started.elapsed() >= shutdown_timeout {
+                    let mut active_tasks = handler.get_service::<Timer>().describe_active();
+                    active_tasks.extend(handler.get_service::<Watchdog>().describe_active());
+                    interface.report_shutdown_timeout(
+                        shutdown_timeout, handler.refcount() - 1, &active_tasks,
+                    );
+                    break
+                }
                 if (ct % 500) == 100 {
                     info!(
                         "Waiting on {} threads to stop. Press {}+C to force shutdown.",
@@ -211,10 +658,53 @@ impl <R: Module> SylphieCore<R> {
                 ct += 1;
                 thread::sleep(Duration::from_millis(10));
             }
+        });
+        self.logger = None;
+    }
+}
+impl <R: Module> Drop for BootedCore<R> {
+    fn drop(&mut self) {
+        self.shutdown_inner();
+    }
+}
 
-            Ok(())
-        })?;
-        Ok(())
+/// Shuts down the bot when a termination signal is received, and forcefully exits the process
+/// if one is received again.
+///
+/// The bot is shut down in the same way as [`SylphieCoreHandlerExt::shutdown_bot`].
+///
+/// `SIGINT` is only handled in headless mode, as the terminal handles it otherwise.
+#[cfg(unix)]
+async fn handle_signals(target: Handler<impl Events>) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let interface = target.get_service::<Interface>();
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = if interface.is_headless() {
+        Some(signal(SignalKind::interrupt())?)
+    } else {
+        None
+    };
+
+    let mut is_shutting_down = false;
+    loop {
+        let name = match &mut sigint {
+            Some(sigint) => tokio::select! {
+                _ = sigterm.recv() => "SIGTERM",
+                _ = sigint.recv() => "SIGINT",
+            },
+            None => {
+                sigterm.recv().await;
+                "SIGTERM"
+            }
+        };
+        if is_shutting_down {
+            eprintln!("Received {} again. Forcefully shutting down.", name);
+            std::process::exit(1);
+        }
+        is_shutting_down = true;
+        info!("Received {}. Shutting down. Send it again to force shutdown.", name);
+        target.shutdown_bot();
     }
 }
 
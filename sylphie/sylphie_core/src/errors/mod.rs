@@ -1,4 +1,5 @@
 use backtrace::Backtrace;
+use crate::i18n::Message;
 use static_events::prelude_async::*;
 use std::borrow::Cow;
 use std::error::{Error as StdError};
@@ -23,9 +24,10 @@ pub enum ErrorKind {
     Panicked(Cow<'static, str>, Option<PanicLocation>),
     /// An error occurred in a command.
     ///
-    /// These errors are meant to be reported to the user and are not internal errors.
+    /// These errors are meant to be reported to the user and are not internal errors. The
+    /// message is localized for the user it is shown to.
     #[error("Command error occurred: {0}")]
-    CommandError(Cow<'static, str>),
+    CommandError(Message),
 
     /// A wrapped generic error.
     #[error("{0}")]
@@ -41,6 +43,7 @@ fn thread_name() -> String {
 
 struct ErrorData {
     kind: ErrorKind,
+    origin: Option<Cow<'static, str>>,
     thread_name: Option<String>,
     backtrace: Option<Backtrace>,
     ctx_backtraces: Vec<(String, Backtrace)>,
@@ -59,6 +62,7 @@ impl Error {
     pub fn new(kind: ErrorKind) -> Self {
         Error(Box::new(ErrorData {
             kind,
+            origin: None,
             thread_name: None,
             backtrace: None,
             ctx_backtraces: Vec::new(),
@@ -82,6 +86,18 @@ impl Error {
         Error::new(kind).with_backtrace()
     }
 
+    /// Sets the name of the module this error originated from, if none already exists.
+    ///
+    /// This is included in error reports, and is available to handlers of
+    /// [`ErrorReportEvent`](crate::interface::ErrorReportEvent).
+    #[inline(never)] #[cold]
+    pub fn with_origin(mut self, module: impl Into<Cow<'static, str>>) -> Self {
+        if self.0.origin.is_none() {
+            self.0.origin = Some(module.into());
+        }
+        self
+    }
+
     /// Adds a thread name to this error, if none already exists.
     #[inline(never)] #[cold]
     pub fn with_thread_name(mut self) -> Self {
@@ -149,6 +165,11 @@ impl Error {
         &self.0.kind
     }
 
+    /// Returns the name of the module this error originated from, if it is known.
+    pub fn origin(&self) -> Option<&str> {
+        self.0.origin.as_deref()
+    }
+
     /// Returns the backtrace associated with this error.
     pub fn backtrace_thread(&self) -> Cow<'_, str> {
         if let Some(x) = &self.0.thread_name {
@@ -266,6 +287,10 @@ mod private {
     }
     impl BoxFail for Error {
         fn set_cause(mut self, err: &mut Error) {
+            if err.0.origin.is_none() {
+                err.0.origin = self.0.origin.take();
+            }
+
             // Only keep a backtrace at the highest level.
             if err.0.backtrace.is_none() {
                 err.0.backtrace = self.0.backtrace.take();
@@ -382,7 +407,7 @@ pub trait ErrorFromContextExt<T>: Sized {
     ///
     /// When used in a command, Sylphie will display the text directly to the user, instead of
     /// showing an generic error message and logging the error to disk.
-    fn cmd_error<S: Into<Cow<'static, str>>>(self, err: impl FnOnce() -> S) -> Result<T>;
+    fn cmd_error<S: Into<Message>>(self, err: impl FnOnce() -> S) -> Result<T>;
 
     /// Converts an error into an internal error.
     fn internal_err<S: Into<Cow<'static, str>>>(self, err: impl FnOnce() -> S) -> Result<T>;
@@ -393,7 +418,7 @@ impl <T, U: private::ErrorContext<T>> ErrorFromContextExt<T> for U {
         private::ErrorContext::context(self, kind, |e| e.with_backtrace())
     }
     #[inline]
-    fn cmd_error<S: Into<Cow<'static, str>>>(self, err: impl FnOnce() -> S) -> Result<T> {
+    fn cmd_error<S: Into<Message>>(self, err: impl FnOnce() -> S) -> Result<T> {
         let kind = move || ErrorKind::CommandError(err().into());
         private::ErrorContext::context(self, kind, |e| e)
     }
@@ -408,7 +433,8 @@ impl <T, U: private::ErrorContext<T>> ErrorFromContextExt<T> for U {
 /// that it can be converted to using [`Into`].
 ///
 /// When used in a command, Sylphie will display the text directly to the user, instead of
-/// showing an generic error message and logging the error to disk.
+/// showing an generic error message and logging the error to disk. A localized
+/// [`Message`](crate::i18n::Message) may also be given instead of text.
 #[macro_export]
 macro_rules! cmd_error_493fbda1c52048499126605cd31d3dd3 {
     ($format:expr, $($arg:expr),* $(,)?) => {{
@@ -0,0 +1,207 @@
+//! Types used to localize messages shown to users.
+//!
+//! Messages are looked up by key in message catalogs, which map each key to a template such as
+//! `Module '{module}' is now enabled.`. Catalogs are written in a simple format with one
+//! `key = template` pair per line. Blank lines and lines starting with `#` are ignored, and
+//! `\n` can be used in a template to insert a line break.
+
+use crate::errors::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A message that is shown to users, which may be localized.
+#[derive(Clone, Debug)]
+pub enum Message {
+    /// A message that is shown as-is, regardless of the user's locale.
+    Text(Cow<'static, str>),
+    /// A message that is looked up in the message catalogs for the user's locale.
+    Key {
+        /// The key the message is looked up with.
+        key: Cow<'static, str>,
+        /// The arguments substituted into the message's template.
+        args: Vec<(Cow<'static, str>, String)>,
+    },
+}
+impl Message {
+    /// Creates a new message that is looked up by key.
+    pub fn key(key: impl Into<Cow<'static, str>>) -> Self {
+        Message::Key { key: key.into(), args: Vec::new() }
+    }
+
+    /// Adds an argument to this message, to be substituted for `{name}` in its template.
+    ///
+    /// This has no effect on messages that are not looked up by key.
+    pub fn arg(mut self, name: impl Into<Cow<'static, str>>, value: impl fmt::Display) -> Self {
+        if let Message::Key { args, .. } = &mut self {
+            args.push((name.into(), value.to_string()));
+        }
+        self
+    }
+
+    /// Renders this message using the first catalog that contains its key.
+    ///
+    /// If no catalog contains the key, the key and its arguments are shown instead.
+    pub fn render(&self, catalogs: &[&Catalog]) -> String {
+        match self {
+            Message::Text(text) => text.to_string(),
+            Message::Key { key, args } => {
+                match catalogs.iter().find_map(|catalog| catalog.get(key)) {
+                    Some(template) => render_template(template, args),
+                    None => self.to_string(),
+                }
+            }
+        }
+    }
+}
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Text(text) => f.write_str(text),
+            Message::Key { key, args } => {
+                f.write_str(key)?;
+                if !args.is_empty() {
+                    f.write_str(" (")?;
+                    for (i, (name, value)) in args.iter().enumerate() {
+                        if i != 0 {
+                            f.write_str(", ")?;
+                        }
+                        write!(f, "{}: {}", name, value)?;
+                    }
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+impl From<&'static str> for Message {
+    fn from(text: &'static str) -> Self {
+        Message::Text(text.into())
+    }
+}
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Message::Text(text.into())
+    }
+}
+impl From<Cow<'static, str>> for Message {
+    fn from(text: Cow<'static, str>) -> Self {
+        Message::Text(text)
+    }
+}
+
+/// Substitutes arguments into a template. Unknown arguments are left as-is.
+fn render_template(template: &str, args: &[(Cow<'static, str>, String)]) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(|c| c == '{' || c == '}') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            out.push_str(&rest[..1]);
+            rest = &rest[2..];
+        } else if rest.starts_with('{') {
+            match rest.find('}') {
+                Some(end) => {
+                    let name = &rest[1..end];
+                    match args.iter().find(|(arg, _)| arg == name) {
+                        Some((_, value)) => out.push_str(value),
+                        None => out.push_str(&rest[..=end]),
+                    }
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    out.push_str(rest);
+                    rest = "";
+                }
+            }
+        } else {
+            out.push('}');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// A set of message templates for a single locale.
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+impl Catalog {
+    /// Parses a catalog from its source.
+    pub fn parse(source: &str) -> Result<Catalog> {
+        let mut messages = HashMap::new();
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let (key, template) = match line.find('=') {
+                Some(pos) => (line[..pos].trim(), line[pos + 1..].trim()),
+                None => bail!("Line {} of message catalog is missing a '='.", i + 1),
+            };
+            ensure!(!key.is_empty(), "Line {} of message catalog has an empty key.", i + 1);
+            ensure!(
+                !messages.contains_key(key),
+                "Line {} of message catalog contains duplicate key '{}'.", i + 1, key,
+            );
+            messages.insert(key.to_string(), template.replace("\\n", "\n"));
+        }
+        Ok(Catalog { messages })
+    }
+
+    /// Adds every message in another catalog to this one, replacing any existing messages with
+    /// the same key.
+    pub fn extend(&mut self, other: Catalog) {
+        self.messages.extend(other.messages);
+    }
+
+    /// Returns the template for a given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(|x| x.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let catalog = Catalog::parse(
+            "# a comment\n\
+             \n\
+             hello = Hello, {name}!\n\
+             multi-line=First\\nSecond\n\
+             equals = a = b\n",
+        ).unwrap();
+        assert_eq!(catalog.get("hello"), Some("Hello, {name}!"));
+        assert_eq!(catalog.get("multi-line"), Some("First\nSecond"));
+        assert_eq!(catalog.get("equals"), Some("a = b"));
+        assert_eq!(catalog.get("missing"), None);
+
+        assert!(Catalog::parse("no separator").is_err());
+        assert!(Catalog::parse(" = no key").is_err());
+        assert!(Catalog::parse("a = 1\na = 2").is_err());
+    }
+
+    #[test]
+    fn render_test() {
+        let catalog = Catalog::parse(
+            "greet = Hello, {name}! You have {count} messages.\n\
+             braces = {{literal}} {missing} }} {unclosed\n",
+        ).unwrap();
+        let catalogs = [&catalog];
+
+        let msg = Message::key("greet").arg("name", "Sylphie").arg("count", 3);
+        assert_eq!(msg.render(&catalogs), "Hello, Sylphie! You have 3 messages.");
+        let msg = Message::key("braces").arg("literal", "unused");
+        assert_eq!(msg.render(&catalogs), "{literal} {missing} } {unclosed");
+        let msg = Message::key("unknown").arg("a", 1);
+        assert_eq!(msg.render(&catalogs), "unknown (a: 1)");
+        assert_eq!(Message::from("text {name}").render(&catalogs), "text {name}");
+    }
+}
@@ -0,0 +1,194 @@
+//! Allows the bot to be administered through a Unix domain socket while it runs detached.
+//!
+//! Each line received on a connection is run as a terminal command, and any messages logged
+//! while running it are sent back to that connection.
+
+use crate::core::dispatch_terminal_command;
+use crate::errors::*;
+use crate::interface::InterfaceShared;
+use crate::interface::{error_report, logger};
+use static_events::prelude_async::*;
+use std::fs::{self, Permissions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::Shutdown;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::Handle;
+
+/// Creates the socket so that only the user the bot runs as can connect to it.
+fn bind(path: &Path) -> Result<UnixListener> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // the lock file prevents other instances from using this path, so this is a stale socket
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+
+    // bind under a temporary name, so the socket is never accessible with the wrong permissions
+    let temp_path = path.with_extension(format!("sock.{}", std::process::id()));
+    if temp_path.exists() {
+        fs::remove_file(&temp_path)?;
+    }
+    let listener = UnixListener::bind(&temp_path)?;
+    fs::set_permissions(&temp_path, Permissions::from_mode(0o600))?;
+    fs::rename(&temp_path, path)?;
+    Ok(listener)
+}
+
+/// A running admin socket. The socket file is removed when this is dropped.
+pub(in super) struct AdminSocket {
+    path: PathBuf,
+}
+impl AdminSocket {
+    pub(in super) fn start<E: Events>(
+        shared: Arc<InterfaceShared>, target: &Handler<E>, path: &Path,
+    ) -> Result<AdminSocket> {
+        let listener = bind(path)
+            .internal_err(|| format!("Could not create admin socket at '{}'.", path.display()))?;
+        listener.set_nonblocking(true)?;
+
+        let target = target.clone();
+        let runtime = Handle::current();
+        thread::Builder::new().name("admin socket listener".to_string()).spawn(move || {
+            let _error_ctx = error_report::enter_ctx(&shared);
+            run_listener(shared, target, runtime, listener)
+        })?;
+
+        info!("Accepting admin connections at '{}'.", path.display());
+        Ok(AdminSocket { path: path.to_owned() })
+    }
+}
+impl Drop for AdminSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn run_listener<E: Events>(
+    shared: Arc<InterfaceShared>, target: Handler<E>, runtime: Handle, listener: UnixListener,
+) {
+    let mut next_id = 0u64;
+    while !shared.is_shutdown.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                next_id += 1;
+                let id = next_id;
+                let shared = shared.clone();
+                let target = target.clone();
+                let runtime = runtime.clone();
+                let result = thread::Builder::new()
+                    .name(format!("admin connection #{}", id))
+                    .spawn(move || {
+                        let _error_ctx = error_report::enter_ctx(&shared);
+                        if let Err(e) = run_connection(&shared, &target, &runtime, stream, id) {
+                            logger::with_current_logger(&shared, || {
+                                warn!("Admin connection #{} failed: {}", id, e);
+                            });
+                        }
+                    });
+                if let Err(e) = result {
+                    logger::with_current_logger(&shared, || {
+                        error!("Could not start thread for admin connection: {}", e);
+                    });
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock =>
+                thread::sleep(Duration::from_millis(100)),
+            Err(e) => {
+                logger::with_current_logger(&shared, || {
+                    error!("Could not accept admin connection: {}", e);
+                });
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+    }
+}
+
+fn run_connection<E: Events>(
+    shared: &InterfaceShared, target: &Handler<E>, runtime: &Handle, stream: UnixStream, id: u64,
+) -> io::Result<()> {
+    // some platforms let accepted sockets inherit nonblocking mode from the listener
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_millis(100)))?;
+    logger::with_current_logger(shared, || debug!("Admin connection #{} opened.", id));
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut buf = Vec::new();
+    while !shared.is_shutdown.load(Ordering::Relaxed) {
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buf).trim().to_string();
+                buf.clear();
+                if !line.is_empty() {
+                    run_command(shared, target, runtime, &stream, id, line)?;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock ||
+                      e.kind() == io::ErrorKind::TimedOut => { }
+            Err(e) => return Err(e),
+        }
+    }
+
+    logger::with_current_logger(shared, || debug!("Admin connection #{} closed.", id));
+    Ok(())
+}
+
+fn run_command<E: Events>(
+    shared: &InterfaceShared, target: &Handler<E>, runtime: &Handle, stream: &UnixStream,
+    id: u64, line: String,
+) -> io::Result<()> {
+    let mut output = stream.try_clone()?;
+    logger::with_current_logger(shared, || {
+        info!("Admin connection #{} ran command: {}", id, line);
+        logger::forward_output(
+            move |msg| {
+                // the client may disconnect at any time, which is not an error
+                let _ = writeln!(output, "{}", msg);
+            },
+            || runtime.block_on(dispatch_terminal_command(target, line)),
+        );
+    });
+    Ok(())
+}
+
+/// Connects to a running bot's admin socket and runs commands on it.
+///
+/// If no commands are given, commands are read from standard input until it is closed.
+pub(crate) fn run_client(path: &Path, commands: Vec<String>) -> Result<()> {
+    let stream = UnixStream::connect(path).internal_err(|| format!(
+        "Could not connect to the admin socket at '{}'. Is the bot running?", path.display(),
+    ))?;
+
+    let mut input = stream.try_clone()?;
+    if commands.is_empty() {
+        thread::Builder::new().name("stdin reader".to_string()).spawn(move || {
+            let stdin = io::stdin();
+            for line in stdin.lock().lines() {
+                match line {
+                    Ok(line) => if writeln!(input, "{}", line).is_err() {
+                        break
+                    },
+                    Err(_) => break,
+                }
+            }
+            let _ = input.shutdown(Shutdown::Write);
+        })?;
+    } else {
+        for command in commands {
+            writeln!(input, "{}", command)?;
+        }
+        input.shutdown(Shutdown::Write)?;
+    }
+
+    let stdout = io::stdout();
+    io::copy(&mut &stream, &mut stdout.lock())?;
+    Ok(())
+}
@@ -2,24 +2,212 @@
 
 use backtrace::Backtrace;
 use crate::errors::*;
-use crate::global_instance::*;
 use crate::interface::InterfaceShared;
+use crate::interface::logger;
 use chrono::Utc;
-use lazy_static::*;
-use parking_lot::Once;
+use parking_lot::{Mutex, Once, const_mutex};
 use parking_lot::deadlock;
-use std::collections::BTreeMap;
+use static_events::prelude_async::*;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
 use std::fmt::{self, Formatter};
 use std::fs;
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io::{Write as IoWrite};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-// TODO: Add some kinda hook for reporting error location correctly for stuff like Minnie errors
-//       where further backtraces/etc are available.
+/// The kind of problem an error report was written for.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ErrorReportKind {
+    /// An error was returned and reported with [`Error::report_error`].
+    Error,
+    /// A panic was caught and reported with [`Error::report_error`].
+    Panic,
+    /// The bot failed to shut down within its grace period.
+    ShutdownTimeout,
+    /// Operations tracked by the [`Watchdog`](crate::watchdog::Watchdog) ran for longer than
+    /// its threshold.
+    StuckTasks,
+    /// A deadlock was detected. The process is aborted after these are reported.
+    Deadlock,
+}
+
+/// This event is dispatched after an error report has been written, allowing modules to forward
+/// reports elsewhere, such as to a chat channel, a webhook or a local collector.
+///
+/// Reports that are suppressed as duplicates or due to rate limiting are not dispatched. This
+/// event is also not dispatched for deadlocks, or while the bot is starting up or shutting down.
+/// Errors reported by handlers of this event are written to disk, but do not cause this event to
+/// be dispatched again.
+///
+/// This event is dispatched synchronously, often from the runtime thread the error was reported
+/// on. Handlers must not block, and should spawn a task for anything that uses the network,
+/// such as sending a chat message or calling a webhook.
+pub struct ErrorReportEvent {
+    kind: ErrorReportKind,
+    summary: String,
+    report: String,
+    origin: Option<String>,
+    thread_name: Option<String>,
+    hash: u64,
+    report_file: PathBuf,
+}
+self_event!(ErrorReportEvent);
+impl ErrorReportEvent {
+    /// Returns the kind of problem this report was written for.
+    pub fn kind(&self) -> ErrorReportKind {
+        self.kind
+    }
+
+    /// Returns a one line summary of the error.
+    pub fn summary(&self) -> &str {
+        &self.summary
+    }
+
+    /// Returns the full text of the error report, as written to disk.
+    pub fn report(&self) -> &str {
+        &self.report
+    }
+
+    /// Returns the name of the module the error originated from, if it is known.
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    /// Returns the name of the thread the error occurred in, if it is known.
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread_name.as_deref()
+    }
+
+    /// Returns a hash identifying where the error occurred, used to detect duplicate reports.
+    ///
+    /// This is only stable for a single build of the bot.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the path the error report was written to.
+    pub fn report_file(&self) -> &Path {
+        &self.report_file
+    }
+}
+
+/// The function used to dispatch [`ErrorReportEvent`] to the running bot.
+pub(in super) type ReportSink = Box<dyn Fn(ErrorReportEvent) + Send + Sync>;
+
+/// Settings controlling how often error reports are written.
+#[derive(Clone, Debug)]
+pub(crate) struct ReportSettings {
+    pub report_url: Option<String>,
+    pub dedup_window: Duration,
+    pub rate_limit: u32,
+    pub rate_limit_period: Duration,
+}
+
+enum ReportAction {
+    Write { suppressed: u64 },
+    Duplicate(Option<PathBuf>),
+    RateLimited,
+}
+
+/// Tracks recently written error reports, to suppress duplicates and hot-looping errors.
+pub(in super) struct ReportLimiter {
+    recent_hashes: HashMap<u64, (Instant, Option<PathBuf>)>,
+    recent_reports: VecDeque<Instant>,
+    suppressed: u64,
+}
+impl ReportLimiter {
+    pub(in super) fn new() -> Self {
+        ReportLimiter {
+            recent_hashes: HashMap::new(),
+            recent_reports: VecDeque::new(),
+            suppressed: 0,
+        }
+    }
+
+    fn check(&mut self, settings: &ReportSettings, hash: u64) -> ReportAction {
+        let now = Instant::now();
+        let dedup_window = settings.dedup_window;
+        self.recent_hashes.retain(|_, (time, _)| now.duration_since(*time) < dedup_window);
+        while let Some(time) = self.recent_reports.front() {
+            if now.duration_since(*time) < settings.rate_limit_period {
+                break
+            }
+            self.recent_reports.pop_front();
+        }
+
+        if let Some((_, path)) = self.recent_hashes.get(&hash) {
+            self.suppressed += 1;
+            ReportAction::Duplicate(path.clone())
+        } else if self.recent_reports.len() >= settings.rate_limit as usize {
+            self.suppressed += 1;
+            ReportAction::RateLimited
+        } else {
+            if dedup_window > Duration::from_secs(0) {
+                self.recent_hashes.insert(hash, (now, None));
+            }
+            self.recent_reports.push_back(now);
+            ReportAction::Write { suppressed: mem::replace(&mut self.suppressed, 0) }
+        }
+    }
+
+    fn set_report_file(&mut self, hash: u64, path: &Path) {
+        if let Some((_, report_file)) = self.recent_hashes.get_mut(&hash) {
+            *report_file = Some(path.to_owned());
+        }
+    }
+}
+
+thread_local! {
+    static IS_DISPATCHING_REPORT: Cell<bool> = Cell::new(false);
+    static CURRENT_CTX: RefCell<Option<ErrorCtx>> = RefCell::new(None);
+}
+
+/// Information about an error report, other than its text.
+struct ReportMetadata {
+    kind: ErrorReportKind,
+    origin: Option<String>,
+    thread_name: Option<String>,
+    hash: u64,
+}
+impl ReportMetadata {
+    fn for_error(err: &Error) -> Self {
+        let mut hasher = DefaultHasher::new();
+        mem::discriminant(err.error_kind()).hash(&mut hasher);
+        match err.backtrace() {
+            Some(bt) => for frame in bt.frames() {
+                (frame.ip() as usize).hash(&mut hasher);
+            },
+            None => err.to_string().hash(&mut hasher),
+        }
+        ReportMetadata {
+            kind: match err.error_kind() {
+                ErrorKind::Panicked(..) => ErrorReportKind::Panic,
+                _ => ErrorReportKind::Error,
+            },
+            origin: err.origin().map(|x| x.to_string()),
+            thread_name: Some(err.backtrace_thread().into_owned()),
+            hash: hasher.finish(),
+        }
+    }
+
+    fn for_report(kind: ErrorReportKind, report: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        report.hash(&mut hasher);
+        ReportMetadata { kind, origin: None, thread_name: None, hash: hasher.finish() }
+    }
+
+    fn is_limited(&self) -> bool {
+        self.kind == ErrorReportKind::Error || self.kind == ErrorReportKind::Panic
+    }
+}
 
 // the type from parking_lot is a Voldemort type
 // this mainly exists so we can have an owned version
@@ -35,18 +223,39 @@ fn check_deadlock() -> Vec<Vec<DeadlockInfo>> {
     }).collect()).collect()
 }
 
-lazy_static! {
-    static ref CURRENT_CTX: GlobalInstance<ErrorCtx> = GlobalInstance::new();
+static RUNNING_CTXS: Mutex<Vec<Weak<InterfaceShared>>> = const_mutex(Vec::new());
+
+/// Sets the bot core errors on the current thread are reported to, until the guard is dropped.
+#[must_use]
+pub struct ErrorCtxGuard(Option<ErrorCtx>);
+impl Drop for ErrorCtxGuard {
+    fn drop(&mut self) {
+        let old = self.0.take();
+        let _ = CURRENT_CTX.try_with(|ctx| *ctx.borrow_mut() = old);
+    }
 }
 
+/// The error reporting context of a single bot core.
 #[derive(Clone)]
-pub struct ErrorCtx(Arc<InterfaceShared>);
+struct ErrorCtx(Arc<InterfaceShared>);
 impl ErrorCtx {
-    pub(in super) fn new(shared: Arc<InterfaceShared>) -> Self {
-        ErrorCtx(shared)
+    /// Returns the context for the current thread.
+    ///
+    /// Threads not started by a bot core are only attributed to one if it is the only bot core
+    /// running in the process.
+    fn current() -> Option<ErrorCtx> {
+        let ctx = CURRENT_CTX.try_with(|ctx| ctx.borrow().clone()).ok().flatten();
+        ctx.or_else(|| {
+            let mut running = ErrorCtx::running();
+            if running.len() == 1 { running.pop() } else { None }
+        })
     }
-    pub fn activate(self) -> InstanceScopeGuard<ErrorCtx> {
-        CURRENT_CTX.set_instance(self)
+
+    /// Returns the context of every bot core running in the process.
+    fn running() -> Vec<ErrorCtx> {
+        let mut ctxs = RUNNING_CTXS.lock();
+        ctxs.retain(|x| x.strong_count() > 0);
+        ctxs.iter().filter_map(|x| x.upgrade()).map(ErrorCtx).collect()
     }
 
     fn fmt_info(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -87,12 +296,24 @@ impl ErrorCtx {
         self.fmt_info(fmt)
     }
     fn fmt_logs(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // TODO: Logs
-        Ok(())
+        match self.0.log_buffer.recent_lines() {
+            Some(lines) if lines.is_empty() => Ok(()),
+            Some(lines) => {
+                write!(fmt, "\nRecent log messages:\n")?;
+                for line in lines {
+                    writeln!(fmt, "    {}", line)?;
+                }
+                Ok(())
+            }
+            None => write!(fmt, "\nRecent log messages: (could not lock log buffer)\n"),
+        }
     }
 }
 fn fmt_error(fmt: &mut fmt::Formatter<'_>, e: &Error) -> fmt::Result {
     write!(fmt, "\nThread '{}' encountered an error: {}\n", e.backtrace_thread(), e)?;
+    if let Some(origin) = e.origin() {
+        write!(fmt, "Originating module: {}\n", origin)?;
+    }
     let mut current = e.source();
     while let Some(source) = current {
         write!(fmt, "Caused by: {}\n", e)?;
@@ -154,38 +375,112 @@ fn write_report_file(logs_path: &Path, report: &str) -> Result<PathBuf> {
     Ok(path)
 }
 
-fn write_report(lock: InstanceGuard<ErrorCtx>, report: &str) -> Result<()> {
-    if !lock.is_loaded() {
-        error!("Error encounted during startup/shutdown:\n{}", report);
-    } else {
-        struct FormatErrorReport<'a>(&'a ErrorCtx, &'a str);
-        impl <'a> fmt::Display for FormatErrorReport<'a> {
-            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-                self.0.fmt_header(f)?;
-                f.write_str(self.1)?;
-                self.0.fmt_logs(f)?;
-                Ok(())
-            }
+fn write_report(ctx: Option<&ErrorCtx>, report: &str, meta: ReportMetadata) -> Result<()> {
+    match ctx {
+        Some(ctx) => logger::with_current_logger(&ctx.0, || write_report_for(ctx, report, meta)),
+        None => {
+            error!("Error encountered outside of a running bot:\n{}", report);
+            Ok(())
         }
+    }
+}
+
+fn write_report_for(ctx: &ErrorCtx, report: &str, meta: ReportMetadata) -> Result<()> {
+    struct FormatErrorReport<'a>(&'a ErrorCtx, &'a str);
+    impl <'a> fmt::Display for FormatErrorReport<'a> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            self.0.fmt_header(f)?;
+            f.write_str(self.1)?;
+            self.0.fmt_logs(f)?;
+            Ok(())
+        }
+    }
 
-        let full_error = FormatErrorReport(&*lock, report).to_string();
-        if let Some(line) = report.trim().split('\n').next() {
-            error!("{}", line);
+    let shared = &ctx.0;
+    let summary = report.trim().split('\n').next().unwrap_or("").to_string();
+    error!("{}", summary);
+
+    if meta.is_limited() {
+        let settings = &shared.info.report_settings;
+        let action = shared.report_limiter.lock().check(settings, meta.hash);
+        match action {
+            ReportAction::Write { suppressed: 0 } => { }
+            ReportAction::Write { suppressed } =>
+                warn!("{} error reports were suppressed since the last one.", suppressed),
+            ReportAction::Duplicate(Some(path)) => {
+                error!("This error was already reported recently at '{}'.", path.display());
+                return Ok(())
+            }
+            ReportAction::Duplicate(None) => {
+                error!("This error was already reported recently.");
+                return Ok(())
+            }
+            ReportAction::RateLimited => {
+                error!("Too many errors have been reported recently. No report was written.");
+                return Ok(())
+            }
         }
+    }
 
-        // TODO: Better logs dir handling
-        let mut logs_dir = lock.0.info.root_path.clone();
-        logs_dir.push("logs");
-        let report_file = write_report_file(&logs_dir, &full_error)?;
-        error!(
-            "Detailed information about this error can be found at '{}'.", report_file.display(),
-        );
-        // TODO: Proper way to handle error reporting URLs.
-        error!("This is probably a bug. Please report it at [TODO] and include the error report.");
+    let full_error = FormatErrorReport(ctx, report).to_string();
+    let report_file = write_report_file(&shared.info.log_path, &full_error)?;
+    if meta.is_limited() {
+        shared.report_limiter.lock().set_report_file(meta.hash, &report_file);
+    }
+    error!(
+        "Detailed information about this error can be found at '{}'.", report_file.display(),
+    );
+    match &shared.info.report_settings.report_url {
+        Some(url) => error!(
+            "This is probably a bug. Please report it at {} and include the error report.",
+            url,
+        ),
+        None => error!(
+            "This is probably a bug. Please include the error report when reporting it.",
+        ),
+    }
+
+    if meta.kind != ErrorReportKind::Deadlock {
+        dispatch_report(shared, ErrorReportEvent {
+            kind: meta.kind,
+            summary,
+            report: full_error,
+            origin: meta.origin,
+            thread_name: meta.thread_name,
+            hash: meta.hash,
+            report_file,
+        });
     }
     Ok(())
 }
 
+fn dispatch_report(shared: &InterfaceShared, event: ErrorReportEvent) {
+    if IS_DISPATCHING_REPORT.with(|x| x.get()) {
+        return
+    }
+    if let Some(sink) = shared.report_sink.load_full() {
+        IS_DISPATCHING_REPORT.with(|x| x.set(true));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| sink(event)));
+        IS_DISPATCHING_REPORT.with(|x| x.set(false));
+        if result.is_err() {
+            error!("A handler for an error report panicked.");
+        }
+    }
+}
+
+/// Registers a new bot core, so deadlocks are reported to it.
+pub(in super) fn register_ctx(shared: &Arc<InterfaceShared>) {
+    let mut ctxs = RUNNING_CTXS.lock();
+    ctxs.retain(|x| x.strong_count() > 0);
+    ctxs.push(Arc::downgrade(shared));
+}
+
+/// Reports errors on the current thread to the given bot core, until the guard is dropped.
+pub(in super) fn enter_ctx(shared: &Arc<InterfaceShared>) -> ErrorCtxGuard {
+    let ctx = Some(ErrorCtx(shared.clone()));
+    ErrorCtxGuard(CURRENT_CTX.with(|current| current.replace(ctx)))
+}
+
 pub fn init_deadlock_detection() {
     struct FormatDeadlock<'a>(&'a Vec<Vec<DeadlockInfo>>);
     impl <'a> fmt::Display for FormatDeadlock<'a> {
@@ -201,10 +496,19 @@ pub fn init_deadlock_detection() {
                 thread::sleep(Duration::from_secs(10));
                 let deadlock = check_deadlock();
                 if !deadlock.is_empty() {
-                    if let Err(e) = write_report(
-                        CURRENT_CTX.load(), &FormatDeadlock(&deadlock).to_string(),
-                    ) {
-                        error!("Error while reporting deadlock: {}", e);
+                    let report = FormatDeadlock(&deadlock).to_string();
+                    // the whole process is aborted, so every running bot core gets a report
+                    let running = ErrorCtx::running();
+                    let ctxs = if running.is_empty() {
+                        vec![None]
+                    } else {
+                        running.iter().map(Some).collect()
+                    };
+                    for ctx in ctxs {
+                        let meta = ReportMetadata::for_report(ErrorReportKind::Deadlock, &report);
+                        if let Err(e) = write_report(ctx, &report, meta) {
+                            error!("Error while reporting deadlock: {}", e);
+                        }
                     }
                     std::process::abort();
                 }
@@ -220,19 +524,59 @@ pub fn report_error(err: &Error) {
         }
     }
 
-    if let Err(e) = write_report(
-        CURRENT_CTX.load(), &FormatError(err).to_string(),
-    ) {
+    let ctx = ErrorCtx::current();
+    let meta = ReportMetadata::for_error(err);
+    if let Err(e) = write_report(ctx.as_ref(), &FormatError(err).to_string(), meta) {
         error!("Error while reporting error: {}", e);
     }
 }
 
-pub(crate) fn get_info_string() -> String {
+pub(in super) fn report_shutdown_timeout(
+    shared: &Arc<InterfaceShared>, timeout: Duration, refcount: usize, active_tasks: &[String],
+) {
+    let mut report = format!(
+        "\nShutdown did not complete within {:?}. {} references to the event handler were \
+         still held.\n",
+        timeout, refcount,
+    );
+    if active_tasks.is_empty() {
+        report.push_str("No timer tasks or watched operations were still active.\n");
+    } else {
+        report.push_str("Active timer tasks and watched operations:\n");
+        for task in active_tasks {
+            report.push_str(&format!("    {}\n", task));
+        }
+    }
+
+    let meta = ReportMetadata::for_report(ErrorReportKind::ShutdownTimeout, &report);
+    if let Err(e) = write_report(Some(&ErrorCtx(shared.clone())), &report, meta) {
+        error!("Error while reporting shutdown timeout: {}", e);
+    }
+}
+
+pub(in super) fn report_stuck_tasks(
+    shared: &Arc<InterfaceShared>, threshold: Duration, tasks: &[String],
+) {
+    let mut report = format!(
+        "\n{} operations have been running for longer than {:?}.\n", tasks.len(), threshold,
+    );
+    report.push_str("Stuck operations:\n");
+    for task in tasks {
+        report.push_str(&format!("    {}\n", task));
+    }
+
+    let meta = ReportMetadata::for_report(ErrorReportKind::StuckTasks, &report);
+    if let Err(e) = write_report(Some(&ErrorCtx(shared.clone())), &report, meta) {
+        error!("Error while reporting stuck operations: {}", e);
+    }
+}
+
+pub(in super) fn get_info_string(shared: &Arc<InterfaceShared>) -> String {
     struct FormatInfo<'a>(&'a ErrorCtx);
     impl <'a> fmt::Display for FormatInfo<'a> {
         fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
             self.0.fmt_info(f)
         }
     }
-    format!("{}", FormatInfo(&*CURRENT_CTX.load()))
+    format!("{}", FormatInfo(&ErrorCtx(shared.clone())))
 }
\ No newline at end of file
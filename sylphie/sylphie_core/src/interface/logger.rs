@@ -1,31 +1,80 @@
-use chrono::Local;
+use arc_swap::{ArcSwap, ArcSwapOption};
+use chrono::{Local, NaiveDate};
 use crate::errors::*;
 use crate::interface::InterfaceShared;
 use crate::interface::terminal::Terminal;
-use parking_lot::Once;
+use parking_lot::{Mutex, Once};
+use serde_json::{Map, Value};
 use static_events::prelude_async::*;
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{Result as FmtResult, Write};
-use std::path::PathBuf;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
-use tracing::{*, Metadata, Event};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing::{*, Dispatch, Metadata, Event};
+use tracing::field::{Field, Visit};
 use tracing::span::{Attributes, Record};
-use tracing::subscriber::DefaultGuard;
-use tracing_subscriber::{FmtSubscriber, EnvFilter};
+use tracing::subscriber::{DefaultGuard, Interest};
+use tracing_subscriber::{EnvFilter, Registry};
+use tracing_subscriber::fmt::{self, MakeWriter};
 use tracing_subscriber::fmt::format::{DefaultFields, Format, Full};
 use tracing_subscriber::fmt::time::FormatTime;
 use tracing_subscriber::filter::Directive;
+use tracing_subscriber::layer::{Context, Layer, Layered, SubscriberExt};
 
-// TODO: Logging to file.
+type ConsoleLayer = fmt::Layer<Registry, DefaultFields, Format<Full, ShortFormatTime>>;
+type FileLayer =
+    fmt::Layer<Registry, DefaultFields, Format<Full, FileFormatTime>, RotatingLogWriter>;
+type BufferLayer =
+    fmt::Layer<Registry, DefaultFields, Format<Full, FileFormatTime>, LogBufferWriter>;
 
-type EnvSubscriber = FmtSubscriber<DefaultFields, Format<Full, ShortFormatTime>, EnvFilter>;
+/// Allows the logger's outputs to be replaced while it is in use, from any thread.
+///
+/// Span data is kept in the [`Registry`], so spans remain valid when the outputs are replaced.
+struct ReloadableLayer(Arc<ArcSwap<LoggerLayer>>);
+impl Layer<Registry> for ReloadableLayer {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        self.0.load().register_callsite(metadata)
+    }
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, Registry>) -> bool {
+        self.0.load().enabled(metadata, ctx)
+    }
+    fn new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, Registry>) {
+        self.0.load().new_span(attrs, id, ctx)
+    }
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, Registry>) {
+        self.0.load().on_record(id, values, ctx)
+    }
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, Registry>) {
+        self.0.load().on_event(event, ctx)
+    }
+    fn on_enter(&self, id: &Id, ctx: Context<'_, Registry>) {
+        self.0.load().on_enter(id, ctx)
+    }
+    fn on_exit(&self, id: &Id, ctx: Context<'_, Registry>) {
+        self.0.load().on_exit(id, ctx)
+    }
+    fn on_close(&self, id: Id, ctx: Context<'_, Registry>) {
+        self.0.load().on_close(id, ctx)
+    }
+}
 
+/// The subscriber used by the bot's logger. Console output is written while holding the
+/// terminal lock, so it does not interfere with the input prompt.
 struct LockingSubscriber {
     shared: Arc<InterfaceShared>,
-    terminal: Arc<Terminal>,
-    underlying: EnvSubscriber,
+    underlying: Layered<ReloadableLayer, Registry>,
 }
 impl Subscriber for LockingSubscriber {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        self.underlying.register_callsite(metadata)
+    }
     fn enabled(&self, metadata: &Metadata<'_>) -> bool {
         self.underlying.enabled(metadata)
     }
@@ -38,15 +87,294 @@ impl Subscriber for LockingSubscriber {
     fn record_follows_from(&self, span: &Id, follows: &Id) {
         self.underlying.record_follows_from(span, follows)
     }
+    fn event(&self, event: &Event<'_>) {
+        self.underlying.event(event);
+        forward_event(event);
+    }
     fn enter(&self, span: &Id) {
         self.underlying.enter(span)
     }
     fn exit(&self, span: &Id) {
         self.underlying.exit(span)
     }
-    fn event(&self, event: &Event<'_>) {
-        let _guard = self.terminal.lock_write();
-        self.underlying.event(event);
+    fn clone_span(&self, id: &Id) -> Id {
+        self.underlying.clone_span(id)
+    }
+    fn try_close(&self, id: Id) -> bool {
+        self.underlying.try_close(id)
+    }
+    unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
+        if id == TypeId::of::<Self>() {
+            Some(self as *const Self as *const ())
+        } else {
+            self.underlying.downcast_raw(id)
+        }
+    }
+}
+
+/// A layer that only receives events that pass its own filter.
+struct FilteredLayer<L: Layer<Registry>> {
+    filter: EnvFilter,
+    layer: L,
+}
+impl <L: Layer<Registry>> FilteredLayer<L> {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        Layer::<Registry>::register_callsite(&self.filter, metadata)
+    }
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, Registry>) -> bool {
+        Layer::<Registry>::enabled(&self.filter, metadata, ctx)
+    }
+    fn new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, Registry>) {
+        self.filter.new_span(attrs, id, ctx.clone());
+        self.layer.new_span(attrs, id, ctx);
+    }
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, Registry>) {
+        if self.enabled(event.metadata(), ctx.clone()) {
+            self.layer.on_event(event, ctx);
+        }
+    }
+    fn on_enter(&self, id: &Id, ctx: Context<'_, Registry>) {
+        self.filter.on_enter(id, ctx.clone());
+        self.layer.on_enter(id, ctx);
+    }
+    fn on_exit(&self, id: &Id, ctx: Context<'_, Registry>) {
+        self.filter.on_exit(id, ctx.clone());
+        self.layer.on_exit(id, ctx);
+    }
+    fn on_close(&self, id: Id, ctx: Context<'_, Registry>) {
+        self.filter.on_close(id.clone(), ctx.clone());
+        self.layer.on_close(id, ctx);
+    }
+}
+
+fn combine_interest(a: Interest, b: Interest) -> Interest {
+    if a.is_never() && b.is_never() {
+        Interest::never()
+    } else if a.is_always() && b.is_always() {
+        Interest::always()
+    } else {
+        Interest::sometimes()
+    }
+}
+
+/// The format used for log files.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum LogFormat {
+    /// The same human-readable format used on the console.
+    Text,
+    /// One JSON object per line, for consumption by other tools.
+    ///
+    /// Each line contains the `timestamp`, `level`, `target`, the event's `fields`, and the
+    /// `spans` the event occurred in, from outermost to innermost. If any of those spans has a
+    /// `module` field, the innermost one is also included as `module`.
+    Json,
+}
+
+/// Collects the fields of a span or event into a JSON object.
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+impl <'a> Visit for JsonVisitor<'a> {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
+
+/// The fields of a span, stored in its extensions by [`JsonLayer`].
+struct JsonSpanFields(Map<String, Value>);
+
+/// A layer that writes events as JSON objects, one per line.
+struct JsonLayer {
+    writer: RotatingLogWriter,
+}
+impl Layer<Registry> for JsonLayer {
+    fn new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, Registry>) {
+        if let Some(span) = ctx.span(id) {
+            let mut fields = Map::new();
+            attrs.record(&mut JsonVisitor(&mut fields));
+            span.extensions_mut().insert(JsonSpanFields(fields));
+        }
+    }
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, Registry>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<JsonSpanFields>() {
+                values.record(&mut JsonVisitor(&mut fields.0));
+            }
+        }
+    }
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, Registry>) {
+        let metadata = event.metadata();
+
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+
+        let mut module = None;
+        let mut spans = Vec::new();
+        let mut current = if let Some(parent) = event.parent() {
+            ctx.span(parent)
+        } else if event.is_contextual() {
+            ctx.lookup_current()
+        } else {
+            None
+        };
+        while let Some(span) = current {
+            let mut span_fields = match span.extensions().get::<JsonSpanFields>() {
+                Some(fields) => fields.0.clone(),
+                None => Map::new(),
+            };
+            if module.is_none() {
+                if let Some(Value::String(name)) = span_fields.get("module") {
+                    module = Some(name.clone());
+                }
+            }
+            span_fields.insert("name".to_string(), span.name().into());
+            spans.push(Value::Object(span_fields));
+            current = span.parent();
+        }
+        spans.reverse();
+
+        let mut line = Map::new();
+        line.insert("timestamp".to_string(), Local::now().to_rfc3339().into());
+        line.insert("level".to_string(), metadata.level().to_string().into());
+        line.insert("target".to_string(), metadata.target().into());
+        if let Some(module) = module {
+            line.insert("module".to_string(), module.into());
+        }
+        line.insert("fields".to_string(), Value::Object(fields));
+        line.insert("spans".to_string(), Value::Array(spans));
+
+        let mut line = Value::Object(line).to_string();
+        line.push('\n');
+        if let Err(e) = self.writer.make_writer().write_all(line.as_bytes()) {
+            eprintln!("Could not write to log file: {}", e);
+        }
+    }
+}
+
+/// The layer used to write log files, in whichever format was selected.
+enum FileOutput {
+    Text(FileLayer),
+    Json(JsonLayer),
+}
+impl Layer<Registry> for FileOutput {
+    fn new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, Registry>) {
+        match self {
+            FileOutput::Text(layer) => layer.new_span(attrs, id, ctx),
+            FileOutput::Json(layer) => layer.new_span(attrs, id, ctx),
+        }
+    }
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, Registry>) {
+        match self {
+            // the text format shares its formatted fields with the console
+            FileOutput::Text(_) => { }
+            FileOutput::Json(layer) => layer.on_record(id, values, ctx),
+        }
+    }
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, Registry>) {
+        match self {
+            FileOutput::Text(layer) => layer.on_event(event, ctx),
+            FileOutput::Json(layer) => layer.on_event(event, ctx),
+        }
+    }
+    fn on_enter(&self, id: &Id, ctx: Context<'_, Registry>) {
+        match self {
+            FileOutput::Text(layer) => layer.on_enter(id, ctx),
+            FileOutput::Json(layer) => layer.on_enter(id, ctx),
+        }
+    }
+    fn on_exit(&self, id: &Id, ctx: Context<'_, Registry>) {
+        match self {
+            FileOutput::Text(layer) => layer.on_exit(id, ctx),
+            FileOutput::Json(layer) => layer.on_exit(id, ctx),
+        }
+    }
+    fn on_close(&self, id: Id, ctx: Context<'_, Registry>) {
+        match self {
+            FileOutput::Text(layer) => layer.on_close(id, ctx),
+            FileOutput::Json(layer) => layer.on_close(id, ctx),
+        }
+    }
+}
+
+/// Dispatches events to each of the logger's outputs, each with its own set of filters.
+struct LoggerLayer {
+    terminal: Arc<Terminal>,
+    console: FilteredLayer<ConsoleLayer>,
+    file: Option<FilteredLayer<FileOutput>>,
+    buffer: BufferLayer,
+}
+impl Layer<Registry> for LoggerLayer {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        let mut interest = self.console.register_callsite(metadata);
+        if let Some(file) = &self.file {
+            interest = combine_interest(interest, file.register_callsite(metadata));
+        }
+        interest
+    }
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, Registry>) -> bool {
+        self.console.enabled(metadata, ctx.clone()) ||
+            self.file.as_ref().map_or(false, |file| file.enabled(metadata, ctx))
+    }
+    fn new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, Registry>) {
+        self.console.new_span(attrs, id, ctx.clone());
+        if let Some(file) = &self.file {
+            file.new_span(attrs, id, ctx.clone());
+        }
+        self.buffer.new_span(attrs, id, ctx);
+    }
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, Registry>) {
+        self.console.filter.on_record(id, values, ctx.clone());
+        if let Some(file) = &self.file {
+            file.filter.on_record(id, values, ctx.clone());
+            file.layer.on_record(id, values, ctx.clone());
+        }
+        // the formatted fields are shared between every output, so only record them once
+        self.console.layer.on_record(id, values, ctx);
+    }
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, Registry>) {
+        let mut is_logged = false;
+        if self.console.enabled(event.metadata(), ctx.clone()) {
+            let _guard = self.terminal.lock_write();
+            self.console.layer.on_event(event, ctx.clone());
+            is_logged = true;
+        }
+        if let Some(file) = &self.file {
+            if file.enabled(event.metadata(), ctx.clone()) {
+                file.layer.on_event(event, ctx.clone());
+                is_logged = true;
+            }
+        }
+        if is_logged {
+            self.buffer.on_event(event, ctx);
+        }
+    }
+    fn on_enter(&self, id: &Id, ctx: Context<'_, Registry>) {
+        self.console.on_enter(id, ctx.clone());
+        if let Some(file) = &self.file {
+            file.on_enter(id, ctx);
+        }
+    }
+    fn on_exit(&self, id: &Id, ctx: Context<'_, Registry>) {
+        self.console.on_exit(id, ctx.clone());
+        if let Some(file) = &self.file {
+            file.on_exit(id, ctx);
+        }
+    }
+    fn on_close(&self, id: Id, ctx: Context<'_, Registry>) {
+        self.console.on_close(id.clone(), ctx.clone());
+        if let Some(file) = &self.file {
+            file.on_close(id, ctx);
+        }
     }
 }
 
@@ -57,11 +385,343 @@ impl FormatTime for ShortFormatTime {
     }
 }
 
+struct FileFormatTime;
+impl FormatTime for FileFormatTime {
+    fn format_time(&self, w: &mut dyn Write) -> FmtResult {
+        write!(w, "{}", Local::now().format("[%Y-%m-%d %H:%M:%S%.3f]"))
+    }
+}
+
+struct RotatingLogState {
+    date: Option<NaiveDate>,
+    file: Option<File>,
+}
+struct RotatingLogData {
+    log_dir: PathBuf,
+    prefix: String,
+    extension: &'static str,
+    retention: usize,
+    state: Mutex<RotatingLogState>,
+}
+impl RotatingLogData {
+    fn file_name(&self, date: NaiveDate) -> String {
+        format!("{}.{}.{}", self.prefix, date.format("%Y-%m-%d"), self.extension)
+    }
+    fn parse_file_name(&self, name: &str) -> Option<NaiveDate> {
+        let date = name.strip_prefix(&self.prefix)?.strip_prefix('.')?
+            .strip_suffix(self.extension)?.strip_suffix('.')?;
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+    }
+
+    fn remove_old_logs(&self) -> io::Result<()> {
+        if self.retention == 0 {
+            return Ok(())
+        }
+
+        let mut logs = Vec::new();
+        for entry in fs::read_dir(&self.log_dir)? {
+            let entry = entry?;
+            if let Some(date) = entry.file_name().to_str().and_then(|x| self.parse_file_name(x)) {
+                logs.push((date, entry.path()));
+            }
+        }
+        logs.sort_by(|a, b| b.0.cmp(&a.0));
+        for (_, path) in logs.into_iter().skip(self.retention) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn current_file<'a>(&self, state: &'a mut RotatingLogState) -> io::Result<&'a mut File> {
+        let today = Local::today().naive_local();
+        if state.file.is_none() || state.date != Some(today) {
+            let mut path = self.log_dir.clone();
+            path.push(self.file_name(today));
+
+            state.file = None;
+            state.file = Some(OpenOptions::new().create(true).append(true).open(path)?);
+            state.date = Some(today);
+
+            if let Err(e) = self.remove_old_logs() {
+                eprintln!("Could not remove old log files: {}", e);
+            }
+        }
+        Ok(state.file.as_mut().unwrap())
+    }
+}
+
+/// Writes logs to a file in the log directory, starting a new file every day.
+#[derive(Clone)]
+struct RotatingLogWriter(Arc<RotatingLogData>);
+impl RotatingLogWriter {
+    fn new(log_dir: &Path, prefix: String, extension: &'static str, retention: usize) -> Self {
+        RotatingLogWriter(Arc::new(RotatingLogData {
+            log_dir: log_dir.to_owned(),
+            prefix,
+            extension,
+            retention,
+            state: Mutex::new(RotatingLogState { date: None, file: None }),
+        }))
+    }
+}
+impl MakeWriter for RotatingLogWriter {
+    type Writer = RotatingLogWriter;
+    fn make_writer(&self) -> Self::Writer {
+        self.clone()
+    }
+}
+impl io::Write for RotatingLogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.0.state.lock();
+        self.0.current_file(&mut state)?.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        let mut state = self.0.state.lock();
+        match &mut state.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Formats the fields of an event into a single line, with the message first.
+struct MessageVisitor(String);
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.0);
+            self.0 = format!("{:?}{}", value, fields);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+thread_local! {
+    static FORWARD_OUTPUT: RefCell<Option<Box<dyn FnMut(&str)>>> = RefCell::new(None);
+}
+
+/// Sends a copy of an event to the output forwarded by [`forward_output`], if any.
+fn forward_event(event: &Event<'_>) {
+    let metadata = event.metadata();
+    if *metadata.level() > Level::INFO {
+        return
+    }
+    FORWARD_OUTPUT.with(|forward| {
+        // the output may itself log errors, so don't forward anything logged while it runs
+        if let Ok(mut forward) = forward.try_borrow_mut() {
+            if let Some(forward) = &mut *forward {
+                let mut visitor = MessageVisitor(String::new());
+                event.record(&mut visitor);
+                if metadata.target() == "[term]" {
+                    forward(&visitor.0);
+                } else {
+                    forward(&format!("{} {}: {}", metadata.level(), metadata.target(), visitor.0));
+                }
+            }
+        }
+    })
+}
+
+/// Runs a function, sending a copy of every message it logs on the current thread to `output`.
+///
+/// Only messages at the `INFO` level or above are forwarded.
+pub(in super) fn forward_output<T>(
+    output: impl FnMut(&str) + 'static, func: impl FnOnce() -> T,
+) -> T {
+    struct ResetGuard(Option<Box<dyn FnMut(&str)>>);
+    impl Drop for ResetGuard {
+        fn drop(&mut self) {
+            let old = self.0.take();
+            FORWARD_OUTPUT.with(|forward| *forward.borrow_mut() = old);
+        }
+    }
+
+    let old = FORWARD_OUTPUT.with(|forward| forward.borrow_mut().replace(Box::new(output)));
+    let _guard = ResetGuard(old);
+    func()
+}
+
+/// Runs a function with the bot's logger active on the current thread, if it is running.
+///
+/// This is used for threads other than the one the logger was activated on.
+pub(in super) fn with_current_logger<T>(shared: &InterfaceShared, func: impl FnOnce() -> T) -> T {
+    match shared.current_dispatch.load_full() {
+        Some(dispatch) => tracing::dispatcher::with_default(&dispatch, func),
+        None => func(),
+    }
+}
+
+/// The subscriber used by the threads of the bot's async runtime, which forwards everything to
+/// the bot's logger while it is active.
+///
+/// The runtime's threads are started before the logger is activated, so the logger is looked
+/// up for every call rather than being installed directly. Spans may outlive the logger that
+/// created them, so this subscriber gives out its own span IDs, and only forwards calls for a
+/// span to the logger it was created in.
+struct ThreadSubscriber {
+    current: Arc<ArcSwapOption<Dispatch>>,
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, (Arc<Dispatch>, Id)>>,
+}
+impl ThreadSubscriber {
+    fn new(current: Arc<ArcSwapOption<Dispatch>>) -> Self {
+        ThreadSubscriber { current, next_id: AtomicU64::new(1), spans: Mutex::new(HashMap::new()) }
+    }
+
+    fn with_current<T>(&self, default: T, func: impl FnOnce(&Dispatch) -> T) -> T {
+        match &*self.current.load() {
+            Some(dispatch) => func(dispatch),
+            None => default,
+        }
+    }
+
+    /// Returns the logger a span was created in, and its ID in that logger. Spans created while
+    /// no logger was active are not tracked.
+    fn lookup(&self, id: &Id) -> Option<(Arc<Dispatch>, Id)> {
+        self.spans.lock().get(&id.into_u64()).cloned()
+    }
+    fn with_span(&self, id: &Id, func: impl FnOnce(&Dispatch, &Id)) {
+        if let Some((dispatch, inner)) = self.lookup(id) {
+            func(&dispatch, &inner)
+        }
+    }
+}
+impl Subscriber for ThreadSubscriber {
+    fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest {
+        // the logger may be activated or replaced at any time
+        Interest::sometimes()
+    }
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.with_current(false, |x| x.enabled(metadata))
+    }
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        if let Some(dispatch) = self.current.load_full() {
+            let inner = dispatch.new_span(span);
+            self.spans.lock().insert(id, (dispatch, inner));
+        }
+        Id::from_u64(id)
+    }
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        self.with_span(span, |dispatch, span| dispatch.record(span, values))
+    }
+    fn record_follows_from(&self, span: &Id, follows: &Id) {
+        if let (Some((dispatch, span)), Some((follows_dispatch, follows))) =
+            (self.lookup(span), self.lookup(follows))
+        {
+            if Arc::ptr_eq(&dispatch, &follows_dispatch) {
+                dispatch.record_follows_from(&span, &follows);
+            }
+        }
+    }
+    fn event(&self, event: &Event<'_>) {
+        self.with_current((), |x| x.event(event))
+    }
+    fn enter(&self, span: &Id) {
+        self.with_span(span, |dispatch, span| dispatch.enter(span))
+    }
+    fn exit(&self, span: &Id) {
+        self.with_span(span, |dispatch, span| dispatch.exit(span))
+    }
+    fn clone_span(&self, id: &Id) -> Id {
+        self.with_span(id, |dispatch, span| { dispatch.clone_span(span); });
+        id.clone()
+    }
+    fn try_close(&self, id: Id) -> bool {
+        match self.lookup(&id) {
+            Some((dispatch, inner)) => {
+                let closed = dispatch.try_close(inner);
+                if closed {
+                    self.spans.lock().remove(&id.into_u64());
+                }
+                closed
+            }
+            None => false,
+        }
+    }
+}
+
+/// Sends everything logged on the current thread to the bot's logger while it is active, for
+/// the rest of the thread's lifetime.
+pub(in super) fn attach_thread(shared: &Arc<InterfaceShared>) {
+    let dispatch = Dispatch::new(ThreadSubscriber::new(shared.current_dispatch.clone()));
+    std::mem::forget(tracing::dispatcher::set_default(&dispatch));
+}
+
+/// Keeps the most recent log messages in memory, so they can be included in error reports.
+pub(in super) struct LogBuffer {
+    lines: Mutex<VecDeque<String>>,
+    capacity: AtomicUsize,
+}
+impl LogBuffer {
+    pub(in super) fn new() -> Self {
+        LogBuffer {
+            lines: Mutex::new(VecDeque::new()),
+            capacity: AtomicUsize::new(DEFAULT_LOG_BUFFER_SIZE),
+        }
+    }
+
+    fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut lines = self.lines.lock();
+        while lines.len() > capacity {
+            lines.pop_front();
+        }
+    }
+    fn push(&self, line: String) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        let mut lines = self.lines.lock();
+        if capacity == 0 {
+            return
+        }
+        while lines.len() >= capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Returns the lines currently in the buffer, or `None` if it could not be locked in time.
+    ///
+    /// This does not block indefinitely, as it may be called while checking for deadlocks.
+    pub(in super) fn recent_lines(&self) -> Option<Vec<String>> {
+        let lines = self.lines.try_lock_for(Duration::from_secs(1))?;
+        Some(lines.iter().cloned().collect())
+    }
+}
+
+/// Writes formatted log messages into the [`LogBuffer`].
+struct LogBufferWriter(Arc<InterfaceShared>);
+impl MakeWriter for LogBufferWriter {
+    type Writer = LogBufferWriter;
+    fn make_writer(&self) -> Self::Writer {
+        LogBufferWriter(self.0.clone())
+    }
+}
+impl io::Write for LogBufferWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let line = String::from_utf8_lossy(buf);
+        self.0.log_buffer.push(line.trim_end().to_string());
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+const DEFAULT_LOG_BUFFER_SIZE: usize = 200;
+
 pub struct Logger {
-    guard: Option<DefaultGuard>,
+    _guard: DefaultGuard,
+    layer: Arc<ArcSwap<LoggerLayer>>,
     shared: Arc<InterfaceShared>,
     terminal: Arc<Terminal>,
 }
+impl Drop for Logger {
+    fn drop(&mut self) {
+        self.shared.current_dispatch.store(None);
+    }
+}
 
 pub fn activate_log_compat() {
     static ONCE: Once = Once::new();
@@ -70,21 +730,73 @@ pub fn activate_log_compat() {
     });
 }
 
+fn parse_directive(directive: &str) -> Option<Directive> {
+    match Directive::from_str(directive) {
+        Ok(x) => Some(x),
+        Err(_) => {
+            error!("Failed to parse logging directive: {}", directive);
+            None
+        }
+    }
+}
+
+/// Checks whether a logging directive such as `sylphie_database=trace` is valid.
+pub fn check_log_directive(directive: &str) -> Result<()> {
+    match Directive::from_str(directive) {
+        Ok(_) => Ok(()),
+        Err(e) => cmd_error!("Invalid logging directive '{}': {}", directive, e),
+    }
+}
+
 /// An event that is sent by the logging framework to configure logging.
 pub struct SetupLoggerEvent {
-    console: tracing_subscriber::EnvFilter,
+    console: EnvFilter,
+    file: EnvFilter,
+    file_enabled: bool,
+    file_format: LogFormat,
+    file_retention: usize,
+    buffer_size: usize,
 }
 self_event!(SetupLoggerEvent);
 impl SetupLoggerEvent {
+    /// Adds a directive controlling which messages are shown on the console.
     pub fn add_console_directive(&mut self, directive: &str) {
-        let directive = match Directive::from_str(directive) {
-            Ok(x) => x,
-            Err(_) => {
-                error!("Failed to parse logging directive: {}", directive);
-                return
-            }
-        };
-        self.console = std::mem::take(&mut self.console).add_directive(directive);
+        if let Some(directive) = parse_directive(directive) {
+            self.console = std::mem::take(&mut self.console).add_directive(directive);
+        }
+    }
+
+    /// Adds a directive controlling which messages are written to the log files.
+    pub fn add_file_directive(&mut self, directive: &str) {
+        if let Some(directive) = parse_directive(directive) {
+            self.file = std::mem::take(&mut self.file).add_directive(directive);
+        }
+    }
+
+    /// Sets whether log files are written at all. This defaults to `true`.
+    pub fn set_file_logging(&mut self, enabled: bool) {
+        self.file_enabled = enabled;
+    }
+
+    /// Sets the format used for log files. This defaults to [`LogFormat::Text`].
+    ///
+    /// This does not affect the console, which is always human-readable.
+    pub fn set_file_format(&mut self, format: LogFormat) {
+        self.file_format = format;
+    }
+
+    /// Sets the number of days of log files that are kept. Older log files are deleted when
+    /// the log file is rotated.
+    ///
+    /// If this is set to `0`, log files are never deleted. This defaults to `14`.
+    pub fn set_file_retention(&mut self, days: usize) {
+        self.file_retention = days;
+    }
+
+    /// Sets the number of recent log messages that are kept in memory to be included in error
+    /// reports. This defaults to `200`.
+    pub fn set_log_buffer_size(&mut self, lines: usize) {
+        self.buffer_size = lines;
     }
 }
 
@@ -102,8 +814,7 @@ pub fn activate_fallback() {
 }
 
 fn log_path(shared: &InterfaceShared) -> Result<PathBuf> {
-    let mut log_path = shared.info.root_path.clone();
-    log_path.push("logs");
+    let log_path = shared.info.log_path.clone();
 
     if !log_path.exists() {
         std::fs::create_dir_all(&log_path)?;
@@ -112,39 +823,113 @@ fn log_path(shared: &InterfaceShared) -> Result<PathBuf> {
 
     Ok(log_path)
 }
-fn make_logger(
+fn make_layer(
     core: &Handler<impl Events>, shared: &Arc<InterfaceShared>, terminal: &Arc<Terminal>,
-) -> Result<LockingSubscriber> {
+) -> Result<LoggerLayer> {
     let log_path = log_path(shared)?;
 
     let ev = core.dispatch_sync(SetupLoggerEvent {
-        console: tracing_subscriber::EnvFilter::new("info"),
+        console: EnvFilter::new("info"),
+        file: EnvFilter::new("info"),
+        file_enabled: true,
+        file_format: LogFormat::Text,
+        file_retention: 14,
+        buffer_size: DEFAULT_LOG_BUFFER_SIZE,
     });
+    shared.log_buffer.set_capacity(ev.buffer_size);
 
-    let subscriber = tracing_subscriber::FmtSubscriber::builder()
+    let console = fmt::layer()
         .with_timer(ShortFormatTime)
-        .with_env_filter(ev.console)
-        .finish();
-    Ok(LockingSubscriber {
-        shared: shared.clone(),
+        .with_ansi(!shared.info.mode.is_headless());
+    let file = if ev.file_enabled {
+        let prefix = shared.info.bot_name.to_lowercase().replace(' ', "-");
+        let layer = match ev.file_format {
+            LogFormat::Text => {
+                let writer = RotatingLogWriter::new(&log_path, prefix, "log", ev.file_retention);
+                FileOutput::Text(
+                    fmt::layer().with_timer(FileFormatTime).with_ansi(false).with_writer(writer),
+                )
+            }
+            LogFormat::Json => {
+                let writer = RotatingLogWriter::new(&log_path, prefix, "json", ev.file_retention);
+                FileOutput::Json(JsonLayer { writer })
+            }
+        };
+        Some(FilteredLayer { filter: ev.file, layer })
+    } else {
+        None
+    };
+    Ok(LoggerLayer {
         terminal: terminal.clone(),
-        underlying: subscriber,
+        console: FilteredLayer { filter: ev.console, layer: console },
+        file,
+        buffer: fmt::layer()
+            .with_timer(FileFormatTime)
+            .with_ansi(false)
+            .with_writer(LogBufferWriter(shared.clone())),
     })
 }
 pub(in super) fn activate(
     core: &Handler<impl Events>, shared: Arc<InterfaceShared>, terminal: Arc<Terminal>,
 ) -> Result<Logger> {
     activate_log_compat();
-    let new_logger = make_logger(core, &shared, &terminal)?;
-    let guard = tracing::subscriber::set_default(new_logger);
-    Ok(Logger { guard: Some(guard), shared, terminal })
+    let layer = Arc::new(ArcSwap::from_pointee(make_layer(core, &shared, &terminal)?));
+    let new_logger = Dispatch::new(LockingSubscriber {
+        shared: shared.clone(),
+        underlying: Registry::default().with(ReloadableLayer(layer.clone())),
+    });
+    let guard = tracing::dispatcher::set_default(&new_logger);
+    shared.current_dispatch.store(Some(Arc::new(new_logger)));
+    Ok(Logger { _guard: guard, layer, shared, terminal })
 }
 pub fn reload(
     core: &Handler<impl Events>, guard: &mut Logger,
 ) -> Result<()> {
     activate_log_compat(); // More a procaution than anything
-    let new_logger = make_logger(core, &guard.shared, &guard.terminal)?;
-    guard.guard = None; // Drop the old guard first. The fallback will take over for a bit.
-    guard.guard = Some(tracing::subscriber::set_default(new_logger)); // Set the new logger.
+    let new_layer = make_layer(core, &guard.shared, &guard.terminal)?;
+    guard.layer.store(Arc::new(new_layer));
+    // the new filters may enable callsites that were previously disabled, or vice versa
+    tracing::callsite::rebuild_interest_cache();
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> Option<Arc<Dispatch>> {
+        Some(Arc::new(Dispatch::new(Registry::default())))
+    }
+
+    #[test]
+    fn thread_subscriber_test() {
+        let meta = tracing::dispatcher::with_default(&Dispatch::new(Registry::default()), || {
+            info_span!("test").metadata().expect("Span is disabled.")
+        });
+        let values: [(&Field, Option<&dyn tracing::Value>); 0] = [];
+        let values = meta.fields().value_set(&values);
+        let attrs = Attributes::new(meta, &values);
+
+        let current = Arc::new(ArcSwapOption::empty());
+        let subscriber = ThreadSubscriber::new(current.clone());
+
+        // a span created before the logger is activated, and dropped after
+        let early = subscriber.new_span(&attrs);
+        current.store(registry());
+        let early_clone = subscriber.clone_span(&early);
+        assert!(!subscriber.try_close(early_clone));
+        assert!(!subscriber.try_close(early));
+
+        // a span that outlives the logger it was created in
+        let span = subscriber.new_span(&attrs);
+        subscriber.enter(&span);
+        subscriber.exit(&span);
+        let span_clone = subscriber.clone_span(&span);
+        current.store(registry());
+        subscriber.enter(&span_clone);
+        subscriber.exit(&span_clone);
+        assert!(!subscriber.try_close(span_clone));
+        assert!(subscriber.try_close(span));
+        assert!(subscriber.spans.lock().is_empty());
+    }
+}
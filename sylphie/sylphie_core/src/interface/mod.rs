@@ -2,43 +2,55 @@
 
 use arc_swap::ArcSwapOption;
 use crate::errors::*;
-use crate::global_instance::InstanceScopeGuard;
 use crate::module::CrateMetadata;
 use parking_lot::Mutex;
 use static_events::prelude_async::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::Dispatch;
 
+#[cfg(unix)] mod admin_socket;
 mod error_report;
 mod logger;
 mod terminal;
 
-pub use logger::SetupLoggerEvent;
-pub use terminal::TerminalCommandEvent;
+pub use error_report::{ErrorReportEvent, ErrorReportKind};
+pub(crate) use error_report::{ErrorCtxGuard, ReportSettings};
+pub use logger::{LogFormat, SetupLoggerEvent, check_log_directive};
+pub use terminal::{TerminalCommandEvent, InterfaceMode, EofBehavior};
 
 // TODO: Replace with BotInfo
 pub(crate) struct InterfaceInfo {
     pub bot_name: String,
-    pub root_path: PathBuf,
+    pub log_path: PathBuf,
+    pub mode: InterfaceMode,
+    pub admin_socket: Option<PathBuf>,
+    pub report_settings: ReportSettings,
 }
 
 struct InterfaceShared {
     info: InterfaceInfo,
     is_shutdown: AtomicBool,
     loaded_crates: ArcSwapOption<Box<[CrateMetadata]>>,
+    log_buffer: logger::LogBuffer,
+    current_dispatch: Arc<ArcSwapOption<Dispatch>>,
+    report_limiter: Mutex<error_report::ReportLimiter>,
+    report_sink: ArcSwapOption<error_report::ReportSink>,
 }
 
 struct InterfaceData {
     shared: Arc<InterfaceShared>,
     terminal: Arc<terminal::Terminal>,
     current_logger: Arc<Mutex<Option<logger::Logger>>>,
-    scope_guard: InstanceScopeGuard<error_report::ErrorCtx>,
 }
-struct LoggerLockGuard<'a>(&'a InterfaceData);
-impl <'a> Drop for LoggerLockGuard<'a> {
+
+/// Keeps the bot's logger active on the current thread until it is dropped.
+pub(crate) struct LoggerGuard(Interface, ErrorCtxGuard);
+impl Drop for LoggerGuard {
     fn drop(&mut self) {
-        *self.0.current_logger.lock() = None;
+        *(self.0).0.current_logger.lock() = None;
     }
 }
 
@@ -51,39 +63,137 @@ impl Interface {
             info,
             is_shutdown: AtomicBool::new(false),
             loaded_crates: ArcSwapOption::empty(),
+            log_buffer: logger::LogBuffer::new(),
+            current_dispatch: Arc::new(ArcSwapOption::empty()),
+            report_limiter: Mutex::new(error_report::ReportLimiter::new()),
+            report_sink: ArcSwapOption::empty(),
         });
-        let error_ctx = error_report::ErrorCtx::new(shared.clone()).activate();
+        error_report::register_ctx(&shared);
         let terminal = Arc::new(terminal::Terminal::new(shared.clone())?);
         Ok(Interface(Arc::new(InterfaceData {
             shared,
             terminal,
             current_logger: Arc::new(Mutex::new(None)),
-            scope_guard: error_ctx,
         })))
     }
 
     pub(crate) fn start(&self, target: &Handler<impl Events>) -> Result<()> {
-        let _lock_guard = {
-            let mut lock = self.0.current_logger.lock();
-            let logger = logger::activate(target, self.0.shared.clone(), self.0.terminal.clone())?;
-            *lock = Some(logger);
-            LoggerLockGuard(&self.0)
-        };
-        self.0.terminal.start_terminal(target)?;
-        Ok(())
+        self.with_logger(target, || {
+            let _admin_socket = self.start_admin_socket(target);
+            self.0.terminal.start_terminal(target)
+        })
+    }
+
+    #[cfg(unix)]
+    fn start_admin_socket(
+        &self, target: &Handler<impl Events>,
+    ) -> Option<admin_socket::AdminSocket> {
+        let path = self.0.shared.info.admin_socket.as_ref()?;
+        match admin_socket::AdminSocket::start(self.0.shared.clone(), target, path) {
+            Ok(socket) => Some(socket),
+            Err(e) => {
+                e.report_error();
+                None
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    fn start_admin_socket(&self, _: &Handler<impl Events>) {
+        if self.0.shared.info.admin_socket.is_some() {
+            warn!("Admin sockets are not supported on this platform.");
+        }
+    }
+
+    /// Runs a function with the bot's logger active, without starting the terminal.
+    pub(crate) fn with_logger<T>(
+        &self, target: &Handler<impl Events>, func: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let _logger_guard = self.activate_logger(target)?;
+        func()
+    }
+
+    /// Activates the bot's logger on the current thread, without starting the terminal.
+    ///
+    /// Errors reported on the current thread are also attributed to this bot.
+    pub(crate) fn activate_logger(&self, target: &Handler<impl Events>) -> Result<LoggerGuard> {
+        let error_ctx = self.enter_error_ctx();
+        let mut lock = self.0.current_logger.lock();
+        let logger = logger::activate(target, self.0.shared.clone(), self.0.terminal.clone())?;
+        *lock = Some(logger);
+        Ok(LoggerGuard(self.clone(), error_ctx))
+    }
+
+    /// Attributes errors reported on the current thread to this bot, until the returned guard
+    /// is dropped.
+    pub(crate) fn enter_error_ctx(&self) -> ErrorCtxGuard {
+        error_report::enter_ctx(&self.0.shared)
+    }
+
+    /// Attributes errors reported on the current thread to this bot and sends its log messages
+    /// to the bot's logger, for the rest of the thread's lifetime. This is used for the threads
+    /// of the bot's async runtime.
+    pub(crate) fn attach_thread(&self) {
+        std::mem::forget(self.enter_error_ctx());
+        logger::attach_thread(&self.0.shared);
+    }
+
+    /// Returns the most recent log messages, in the same format they are written to log files.
+    ///
+    /// The number of messages kept can be configured with
+    /// [`SetupLoggerEvent::set_log_buffer_size`].
+    pub fn recent_logs(&self) -> Vec<String> {
+        self.0.shared.log_buffer.recent_lines().unwrap_or_default()
     }
 
     pub(crate) fn shutdown(&self) {
         self.0.shared.is_shutdown.store(true, Ordering::Relaxed)
     }
 
+    pub(crate) fn is_headless(&self) -> bool {
+        self.0.shared.info.mode.is_headless()
+    }
+
+    /// Reports that the bot failed to shut down within the configured grace period.
+    pub(crate) fn report_shutdown_timeout(
+        &self, timeout: Duration, refcount: usize, active_tasks: &[String],
+    ) {
+        error_report::report_shutdown_timeout(&self.0.shared, timeout, refcount, active_tasks);
+    }
+
+    /// Sets the event handler [`ErrorReportEvent`] is dispatched to.
+    ///
+    /// This must be cleared with [`Interface::clear_report_target`] before the bot shuts down,
+    /// as it keeps a reference to the event handler.
+    pub(crate) fn set_report_target<E: Events>(&self, target: &Handler<E>) {
+        let target = target.clone();
+        let sink: error_report::ReportSink = Box::new(move |ev| {
+            target.dispatch_sync(ev);
+        });
+        self.0.shared.report_sink.store(Some(Arc::new(sink)));
+    }
+
+    pub(crate) fn clear_report_target(&self) {
+        self.0.shared.report_sink.store(None);
+    }
+
+    /// Reports that operations tracked by the watchdog have been running for too long.
+    pub(crate) fn report_stuck_tasks(&self, threshold: Duration, tasks: &[String]) {
+        error_report::report_stuck_tasks(&self.0.shared, threshold, tasks);
+    }
+
+    /// Returns information about the bot's packages and platform, as included in error reports.
+    pub(crate) fn info_string(&self) -> String {
+        error_report::get_info_string(&self.0.shared)
+    }
+
     pub(crate) fn set_loaded_crates(&self, crates: Arc<[CrateMetadata]>) {
         self.0.shared.loaded_crates.store(Some(Arc::new(crates.to_vec().into())));
     }
 
     /// Reloads the logger, to reflect any configuration changes that may have occurred since.
     ///
-    /// If no logger is currently active, this method will return an error.
+    /// This may be called from any thread. If no logger is currently active, this method will
+    /// return an error.
     pub fn reload_logger(&self, target: &Handler<impl Events>) -> Result<()> {
         let mut lock = self.0.current_logger.lock();
         let handle = lock.as_mut().internal_err(|| "Logger is not running.")?;
@@ -103,6 +213,11 @@ pub(crate) fn init_interface() {
     logger::activate_fallback();
     error_report::init_deadlock_detection();
 }
-pub(crate) fn get_info_string() -> String {
-    error_report::get_info_string()
+#[cfg(unix)]
+pub(crate) fn run_admin_client(path: &Path, commands: Vec<String>) -> Result<()> {
+    admin_socket::run_client(path, commands)
+}
+#[cfg(not(unix))]
+pub(crate) fn run_admin_client(_: &Path, _: Vec<String>) -> Result<()> {
+    bail!("Admin sockets are not supported on this platform.")
 }
\ No newline at end of file
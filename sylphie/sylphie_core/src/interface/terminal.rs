@@ -1,67 +1,150 @@
+use crate::core::{SylphieCoreHandlerExt, dispatch_terminal_command};
 use crate::errors::*;
 use crate::interface::InterfaceShared;
 use linefeed::{
     Interface as LinefeedInterface, DefaultTerminal, Signal, ReadResult, Writer,
 };
+use parking_lot::{Mutex, MutexGuard};
 use static_events::prelude_async::*;
+use std::io::{self, BufRead};
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
 use std::time::*;
 
+/// Dispatched when a line is entered in the terminal that is not a built-in command.
+///
+/// Lines starting with a period are dispatched as a
+/// [`BuiltinCommandEvent`](crate::core::BuiltinCommandEvent) instead.
 pub struct TerminalCommandEvent(pub String);
 simple_event!(TerminalCommandEvent);
 
-pub struct TerminalLock<'a, 'b>(Writer<'a, 'b, DefaultTerminal>);
+/// Controls how the bot interacts with the terminal it is started from.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum InterfaceMode {
+    /// An interactive terminal with a prompt and line editing. This requires a TTY.
+    ///
+    /// Once the end of input is reached, such as when Ctrl-D is pressed, no more commands are
+    /// read but the bot keeps running, as with [`EofBehavior::Continue`].
+    Terminal,
+    /// Reads commands from standard input line by line, without a prompt or line editing.
+    ///
+    /// This is meant for running the bot under a service manager or in a container, or for
+    /// piping in commands from a script.
+    Headless(EofBehavior),
+    /// Does not read standard input at all.
+    HeadlessNoInput,
+}
+impl InterfaceMode {
+    pub(in super) fn is_headless(&self) -> bool {
+        *self != InterfaceMode::Terminal
+    }
+}
+impl Default for InterfaceMode {
+    fn default() -> Self {
+        InterfaceMode::Terminal
+    }
+}
+
+/// Controls what happens when the end of standard input is reached in headless mode.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum EofBehavior {
+    /// Stops reading commands, but keeps the bot running.
+    Continue,
+    /// Shuts down the bot.
+    Shutdown,
+}
+
+enum TerminalLockInner<'a, 'b> {
+    Linefeed(Writer<'a, 'b, DefaultTerminal>),
+    Headless(MutexGuard<'a, ()>),
+}
+pub struct TerminalLock<'a, 'b>(TerminalLockInner<'a, 'b>);
+
+enum TerminalBackend {
+    Linefeed(LinefeedInterface<DefaultTerminal>),
+    Headless {
+        mode: InterfaceMode,
+        output_lock: Mutex<()>,
+    },
+}
 
 struct TerminalInfo {
     shared: Arc<InterfaceShared>,
-    interface: LinefeedInterface<DefaultTerminal>,
+    backend: TerminalBackend,
 }
 pub struct Terminal(Arc<TerminalInfo>);
 impl Terminal {
     pub(in super) fn new(shared: Arc<InterfaceShared>) -> Result<Terminal> {
-        let internal_name = shared.info.bot_name.to_lowercase().replace(' ', "-");
-        let interface = LinefeedInterface::new(internal_name.clone())?;
-        interface.set_report_signal(Signal::Interrupt, true);
-        interface.set_report_signal(Signal::Quit, true);
-        interface.set_history_size(100);
-        interface.set_prompt(&format!("{}> ", internal_name))?;
-        Ok(Terminal(Arc::new(TerminalInfo { shared, interface })))
+        let backend = match shared.info.mode {
+            InterfaceMode::Terminal => {
+                let internal_name = shared.info.bot_name.to_lowercase().replace(' ', "-");
+                let interface = LinefeedInterface::new(internal_name.clone())?;
+                interface.set_report_signal(Signal::Interrupt, true);
+                interface.set_report_signal(Signal::Quit, true);
+                interface.set_history_size(100);
+                interface.set_prompt(&format!("{}> ", internal_name))?;
+                TerminalBackend::Linefeed(interface)
+            }
+            mode => TerminalBackend::Headless { mode, output_lock: Mutex::new(()) },
+        };
+        Ok(Terminal(Arc::new(TerminalInfo { shared, backend })))
+    }
+
+    fn is_shutdown(&self) -> bool {
+        self.0.shared.is_shutdown.load(Ordering::Relaxed)
     }
-    fn shutdown_msg(&self) -> Result<()> {
+
+    pub fn start_terminal(&self, target: &Handler<impl Events>) -> Result<()> {
+        match &self.0.backend {
+            TerminalBackend::Linefeed(interface) => self.run_linefeed(interface, target),
+            TerminalBackend::Headless { mode: InterfaceMode::Headless(on_eof), .. } =>
+                self.run_headless(target, *on_eof),
+            TerminalBackend::Headless { .. } => {
+                self.wait_for_shutdown();
+                Ok(())
+            }
+        }
+    }
+
+    fn shutdown_msg(&self, interface: &LinefeedInterface<DefaultTerminal>) -> Result<()> {
         write!(
-            self.0.interface,
+            interface,
             "Please use the '.shutdown' command to stop {}.\n",
             self.0.shared.info.bot_name,
         )?;
         Ok(())
     }
-    pub fn start_terminal(&self, target: &Handler<impl Events>) -> Result<()> {
+    fn run_linefeed(
+        &self, interface: &LinefeedInterface<DefaultTerminal>, target: &Handler<impl Events>,
+    ) -> Result<()> {
         let mut last_failed = false;
         'outer: loop {
-            let result = self.0.interface.read_line_step(Some(Duration::from_millis(100)));
+            let result = interface.read_line_step(Some(Duration::from_millis(100)));
             if result.is_ok() {
                 last_failed = false;
             }
             match result {
                 Ok(Some(ReadResult::Input(line))) => if !line.trim().is_empty() {
-                    self.0.interface.add_history_unique(line.clone());
-
-                    // TODO: Error reporting.
-                    tokio::runtime::Handle::current().block_on(async {
-                        target.dispatch_async(TerminalCommandEvent(line)).await;
-                    });
+                    interface.add_history_unique(line.clone());
+                    dispatch_command(target, line);
                 }
                 Ok(Some(ReadResult::Eof)) => {
-                    self.shutdown_msg()?;
+                    write!(
+                        interface,
+                        "Reached the end of input. No more commands will be read.\n",
+                    )?;
+                    self.wait_for_shutdown();
+                    break 'outer;
                 }
                 Ok(Some(ReadResult::Signal(Signal::Interrupt))) => {
                     eprint!("^C\n");
-                    self.shutdown_msg()?;
-                    self.0.interface.set_buffer("")?;
+                    self.shutdown_msg(interface)?;
+                    interface.set_buffer("")?;
                 }
                 Ok(Some(ReadResult::Signal(Signal::Quit))) => {
-                    write!(self.0.interface, " (killed)\n")?;
+                    write!(interface, " (killed)\n")?;
                     break 'outer;
                 }
                 Ok(Some(ReadResult::Signal(sig))) =>
@@ -77,14 +160,91 @@ impl Terminal {
                     }
                 },
             }
-            if self.0.shared.is_shutdown.load(Ordering::Relaxed) {
-                self.0.interface.cancel_read_line()?;
+            if self.is_shutdown() {
+                interface.cancel_read_line()?;
                 break 'outer;
             }
         }
         Ok(())
     }
+
+    fn run_headless(&self, target: &Handler<impl Events>, on_eof: EofBehavior) -> Result<()> {
+        let mut input = Some(spawn_stdin_reader()?);
+        while !self.is_shutdown() {
+            let recv = match &input {
+                Some(recv) => recv,
+                None => {
+                    self.wait_for_shutdown();
+                    break
+                }
+            };
+            match recv.recv_timeout(Duration::from_millis(100)) {
+                Ok(Some(line)) => if !line.trim().is_empty() {
+                    let line = line.trim_end_matches(&['\r', '\n'][..]);
+                    dispatch_command(target, line.to_string());
+                }
+                Ok(None) | Err(RecvTimeoutError::Disconnected) => {
+                    input = None;
+                    match on_eof {
+                        EofBehavior::Continue => info!(
+                            "Reached the end of standard input. No more commands will be read.",
+                        ),
+                        EofBehavior::Shutdown => {
+                            info!("Reached the end of standard input. Shutting down.");
+                            target.shutdown_bot();
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => { }
+            }
+        }
+        Ok(())
+    }
+
+    fn wait_for_shutdown(&self) {
+        while !self.is_shutdown() {
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
     pub fn lock_write(&self) -> Result<TerminalLock> {
-        Ok(TerminalLock(self.0.interface.lock_writer_erase()?))
+        Ok(TerminalLock(match &self.0.backend {
+            TerminalBackend::Linefeed(interface) =>
+                TerminalLockInner::Linefeed(interface.lock_writer_erase()?),
+            TerminalBackend::Headless { output_lock, .. } =>
+                TerminalLockInner::Headless(output_lock.lock()),
+        }))
     }
 }
+
+fn dispatch_command(target: &Handler<impl Events>, line: String) {
+    // TODO: Error reporting.
+    tokio::runtime::Handle::current().block_on(dispatch_terminal_command(target, line));
+}
+
+/// Reads standard input on a background thread, as it cannot be read with a timeout.
+///
+/// The channel receives `None` once the end of input is reached or an error occurs.
+fn spawn_stdin_reader() -> Result<Receiver<Option<String>>> {
+    let (send, recv) = mpsc::channel();
+    thread::Builder::new().name("stdin reader".to_string()).spawn(move || {
+        let stdin = io::stdin();
+        let mut stdin = stdin.lock();
+        loop {
+            let mut buf = String::new();
+            let line = match stdin.read_line(&mut buf) {
+                Ok(0) => None,
+                Ok(_) => Some(buf),
+                Err(e) => {
+                    error!("Could not read from standard input: {}", e);
+                    None
+                }
+            };
+            let is_eof = line.is_none();
+            if send.send(line).is_err() || is_eof {
+                break
+            }
+        }
+    })?;
+    Ok(recv)
+}
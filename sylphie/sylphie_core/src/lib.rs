@@ -1,16 +1,15 @@
 #![feature(specialization)]
 #![deny(unused_must_use)]
 
-// TODO: Separation of concerns: Less cmd_error in the core? Or should I18N codes be enough...
-
 #[macro_use] extern crate tracing;
 pub mod errors; // this goes before to make sure macros resolve
 
 pub mod core;
-mod global_instance;
+pub mod i18n;
 pub mod interface;
 pub mod module;
 pub mod timer;
+pub mod watchdog;
 
 pub use crate::core::SylphieCore;
 pub use crate::errors::{Result, Error};
@@ -37,6 +36,50 @@ pub mod __macro_priv {
     }
     impl <T> CheckIsComponent<u32> for T { }
     impl <T: IsComponent> CheckIsComponent<u64> for T { }
+
+    // Skips the event handlers of modules that should not receive an event.
+    use crate::module::{FilteredEvent, Module, ModuleInfo};
+    use static_events::prelude_async::*;
+    pub trait MaybeFilteredEvent {
+        fn is_enabled_for_module(&self, target: &Handler<impl Events>, module: &ModuleInfo) -> bool;
+    }
+    impl <T: ?Sized> MaybeFilteredEvent for T {
+        default fn is_enabled_for_module(
+            &self, _: &Handler<impl Events>, _: &ModuleInfo,
+        ) -> bool {
+            true
+        }
+    }
+    impl <T: FilteredEvent + ?Sized> MaybeFilteredEvent for T {
+        fn is_enabled_for_module(
+            &self, target: &Handler<impl Events>, module: &ModuleInfo,
+        ) -> bool {
+            self.is_enabled_for(target, module)
+        }
+    }
+    pub fn is_handler_enabled<M: Module, Ev: ?Sized>(
+        module: &M, target: &Handler<impl Events>, ev: &Ev,
+    ) -> bool {
+        ev.is_enabled_for_module(target, module.info())
+    }
+
+    /// The value returned by event handlers that are skipped.
+    pub trait SkippedHandler {
+        fn skipped() -> Self;
+    }
+    impl SkippedHandler for () {
+        fn skipped() { }
+    }
+    impl SkippedHandler for EventResult {
+        fn skipped() -> Self {
+            EventResult::EvOk
+        }
+    }
+    impl <T: SkippedHandler, E> SkippedHandler for Result<T, E> {
+        fn skipped() -> Self {
+            Ok(T::skipped())
+        }
+    }
 }
 
 /// A convenience module containing common imports that are useful throughout Sylphie-based code.
@@ -53,6 +96,8 @@ pub mod prelude {
 pub mod derives {
     #[doc(inline)] pub use sylphie_derive::{
         CoreModule as Module,
+        CoreParseArg as ParseArg,
+        CoreParseFlags as ParseFlags,
         module_impl_core as module_impl,
         command, config,
     };
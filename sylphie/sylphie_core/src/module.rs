@@ -1,5 +1,11 @@
+use crate::core::BotInfo;
+use crate::errors::*;
+use crate::interface::Interface;
+use crate::timer::Timer;
+use crate::watchdog::Watchdog;
 use enumset::*;
 use static_events::prelude_async::*;
+use std::any::{TypeId, type_name};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, Ordering};
@@ -83,8 +89,17 @@ impl ModuleInfo {
     }
 }
 
+/// A service that a module requires to be present in the module tree.
+struct ServiceRequirement {
+    module: String,
+    service: TypeId,
+    service_name: &'static str,
+}
+
 pub struct ModuleTreeWalker<'a> {
     manager: &'a mut ModuleManager,
+    provided_services: HashSet<TypeId>,
+    required_services: Vec<ServiceRequirement>,
 }
 impl <'a> ModuleTreeWalker<'a> {
     fn init_module(
@@ -111,6 +126,44 @@ impl <'a> ModuleTreeWalker<'a> {
         self.init_module(&submodule_name, metadata, module.info_mut());
         module
     }
+
+    /// Marks a service as being provided by a module in the tree.
+    pub fn provide_service<T: 'static>(&mut self) {
+        self.provided_services.insert(TypeId::of::<T>());
+    }
+
+    /// Marks a service as being required by a given module.
+    pub fn require_service<T: 'static>(&mut self, module: &str) {
+        self.required_services.push(ServiceRequirement {
+            module: if module.is_empty() { "__root__".to_string() } else { module.to_string() },
+            service: TypeId::of::<T>(),
+            service_name: type_name::<T>(),
+        });
+    }
+
+    fn check_requirements(&self) -> Result<()> {
+        let missing: Vec<_> = self.required_services.iter()
+            .filter(|x| !self.provided_services.contains(&x.service))
+            .map(|x| format!("    {} requires {}", x.module, x.service_name))
+            .collect();
+        if !missing.is_empty() {
+            bail!(
+                "Services required by modules are missing from the module tree:\n{}",
+                missing.join("\n"),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// An event that should only be received by some modules, such as an event that occurred in a
+/// scope some modules are disabled in.
+///
+/// Event handlers defined in a `#[module_impl]` block are skipped for modules this returns
+/// `false` for. Handlers without a `self` parameter are always called.
+pub trait FilteredEvent {
+    /// Returns whether the handlers of a given module should receive this event.
+    fn is_enabled_for(&self, target: &Handler<impl Events>, module: &ModuleInfo) -> bool;
 }
 
 pub trait Module: Events + Sized + Send + Sync + 'static {
@@ -160,7 +213,7 @@ impl ModuleManager {
         list.sort();
         self.source_crates = list.into();
     }
-    pub(crate) fn init<R: Module>() -> (ModuleManager, R) {
+    pub(crate) fn init<R: Module>() -> Result<(ModuleManager, R)> {
         static MODULE_ID_ROOT: AtomicU32 = AtomicU32::new(0);
         let mut manager = ModuleManager {
             module_id_root: MODULE_ID_ROOT.fetch_add(1, Ordering::Relaxed),
@@ -170,12 +223,23 @@ impl ModuleManager {
         };
         let mut walker = ModuleTreeWalker {
             manager: &mut manager,
+            provided_services: HashSet::new(),
+            required_services: Vec::new(),
         };
+
+        // services that are always provided by the core
+        walker.provide_service::<ModuleManager>();
+        walker.provide_service::<Interface>();
+        walker.provide_service::<BotInfo>();
+        walker.provide_service::<Timer>();
+        walker.provide_service::<Watchdog>();
+
         let mut root = R::init_module("", &mut walker);
         let metadata = root.metadata();
         walker.init_module("", metadata, root.info_mut());
+        walker.check_requirements()?;
         manager.compute_source_crates();
-        (manager, root)
+        Ok((manager, root))
     }
     pub(crate) fn loaded_crates_list(&self) -> Arc<[CrateMetadata]> {
         self.source_crates.clone()
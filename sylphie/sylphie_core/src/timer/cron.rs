@@ -0,0 +1,209 @@
+use crate::errors::*;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The maximum distance into the future that a cron schedule is searched.
+const SEARCH_LIMIT_DAYS: i64 = 366 * 5;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct FieldSet {
+    bits: u64,
+    is_wildcard: bool,
+}
+impl FieldSet {
+    fn contains(&self, value: u32) -> bool {
+        self.bits & (1 << value as u64) != 0
+    }
+}
+
+fn parse_value(field: &str, value: &str, min: u32, max: u32) -> Result<u32> {
+    let value: u32 = match value.parse() {
+        Ok(value) => value,
+        Err(_) => cmd_error!("Invalid value '{}' in cron field '{}'.", value, field),
+    };
+    if value < min || value > max {
+        cmd_error!(
+            "Value '{}' in cron field '{}' must be between {} and {}.", value, field, min, max,
+        );
+    }
+    Ok(value)
+}
+fn parse_field(field: &str, min: u32, max: u32) -> Result<FieldSet> {
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.find('/') {
+            Some(idx) => (&part[..idx], parse_value(field, &part[idx + 1..], 1, max)?),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some(idx) = range.find('-') {
+            let start = parse_value(field, &range[..idx], min, max)?;
+            let end = parse_value(field, &range[idx + 1..], min, max)?;
+            if start > end {
+                cmd_error!("Range '{}' in cron field '{}' is backwards.", range, field);
+            }
+            (start, end)
+        } else {
+            let value = parse_value(field, range, min, max)?;
+            (value, if step != 1 { max } else { value })
+        };
+
+        let mut i = start;
+        while i <= end {
+            bits |= 1 << i as u64;
+            i += step;
+        }
+    }
+    // as in Vixie cron, fields such as `*/2` count as wildcards, while `1-31` does not
+    Ok(FieldSet { bits, is_wildcard: field.starts_with('*') })
+}
+
+#[derive(Debug)]
+struct CronScheduleData {
+    source: String,
+    minutes: FieldSet,
+    hours: FieldSet,
+    days_of_month: FieldSet,
+    months: FieldSet,
+    days_of_week: FieldSet,
+}
+
+/// A cron-like schedule.
+///
+/// This supports the standard five fields (minute, hour, day of month, month and day of week),
+/// each of which may contain `*`, single values, ranges such as `1-5`, steps such as `*/15` and
+/// comma-separated lists of any of these. As in standard cron, if both the day of month and the
+/// day of week are restricted (do not start with `*`), a day matching either field is accepted.
+#[derive(Clone, Debug)]
+pub struct CronSchedule(Arc<CronScheduleData>);
+impl CronSchedule {
+    /// Parses a cron-like schedule.
+    pub fn parse(source: &str) -> Result<CronSchedule> {
+        let fields: Vec<_> = source.split_whitespace().collect();
+        if fields.len() != 5 {
+            cmd_error!("Cron schedules must have exactly 5 fields, found {}.", fields.len());
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7)?;
+        if days_of_week.contains(7) {
+            // both 0 and 7 are Sunday
+            days_of_week.bits |= 1;
+        }
+
+        Ok(CronSchedule(Arc::new(CronScheduleData {
+            source: fields.join(" "),
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days_of_month: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            days_of_week,
+        })))
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let data = &*self.0;
+        let dom = data.days_of_month.contains(date.day());
+        let dow = data.days_of_week.contains(date.weekday().num_days_from_sunday());
+        match (data.days_of_month.is_wildcard, data.days_of_week.is_wildcard) {
+            (true, true) => true,
+            (false, true) => dom,
+            (true, false) => dow,
+            (false, false) => dom || dow,
+        }
+    }
+
+    fn next_after_naive(&self, after: &NaiveDateTime) -> Option<NaiveDateTime> {
+        let data = &*self.0;
+        let start = after.date().and_hms(after.hour(), after.minute(), 0) + Duration::minutes(1);
+        let limit = start + Duration::days(SEARCH_LIMIT_DAYS);
+
+        let mut time = start;
+        while time < limit {
+            let date = time.date();
+            if !data.months.contains(date.month()) {
+                let (year, month) = if date.month() == 12 {
+                    (date.year() + 1, 1)
+                } else {
+                    (date.year(), date.month() + 1)
+                };
+                time = NaiveDate::from_ymd(year, month, 1).and_hms(0, 0, 0);
+            } else if !self.matches_day(date) {
+                time = date.succ().and_hms(0, 0, 0);
+            } else if !data.hours.contains(time.hour()) {
+                time = date.and_hms(time.hour(), 0, 0) + Duration::hours(1);
+            } else if !data.minutes.contains(time.minute()) {
+                time = time + Duration::minutes(1);
+            } else {
+                return Some(time)
+            }
+        }
+        None
+    }
+
+    /// Returns the first time strictly after the given time at which this schedule fires.
+    ///
+    /// Returns `None` if the schedule can never fire, such as for `0 0 31 2 *`.
+    pub fn next_after(&self, after: &DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut search = after.naive_local();
+        loop {
+            let next = self.next_after_naive(&search)?;
+            // skip times that do not exist due to daylight savings time
+            match Local.from_local_datetime(&next).earliest() {
+                Some(time) if time > *after => return Some(time),
+                _ => search = next,
+            }
+        }
+    }
+}
+impl FromStr for CronSchedule {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        CronSchedule::parse(s)
+    }
+}
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_next(schedule: &str, after: &str, expected: Option<&str>) {
+        let schedule = CronSchedule::parse(schedule).unwrap();
+        let after = NaiveDateTime::parse_from_str(after, "%Y-%m-%d %H:%M").unwrap();
+        let expected = expected.map(|x| NaiveDateTime::parse_from_str(x, "%Y-%m-%d %H:%M").unwrap());
+        assert_eq!(schedule.next_after_naive(&after), expected);
+    }
+
+    #[test]
+    fn parse_test() {
+        assert!(CronSchedule::parse("* * * * *").is_ok());
+        assert!(CronSchedule::parse("*/15 0-6,18 1 */2 1-5").is_ok());
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("* * 0 * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+    }
+
+    #[test]
+    fn next_test() {
+        check_next("* * * * *", "2020-01-01 00:00", Some("2020-01-01 00:01"));
+        check_next("*/15 * * * *", "2020-01-01 00:07", Some("2020-01-01 00:15"));
+        check_next("30 12 * * *", "2020-01-01 12:30", Some("2020-01-02 12:30"));
+        check_next("0 0 1 * *", "2020-12-15 08:00", Some("2021-01-01 00:00"));
+        check_next("0 0 29 2 *", "2021-01-01 00:00", Some("2024-02-29 00:00"));
+        check_next("0 9 * * 1", "2020-01-01 00:00", Some("2020-01-06 09:00"));
+        check_next("0 9 * * 7", "2020-01-01 00:00", Some("2020-01-05 09:00"));
+        check_next("0 0 13 * 5", "2020-03-01 00:00", Some("2020-03-06 00:00"));
+        check_next("0 0 31 2 *", "2020-01-01 00:00", None);
+        check_next("0 0 */1 * 5", "2020-03-01 00:00", Some("2020-03-06 00:00"));
+        check_next("0 0 1-31 * 5", "2020-03-01 00:00", Some("2020-03-02 00:00"));
+    }
+}
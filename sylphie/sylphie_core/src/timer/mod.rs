@@ -0,0 +1,245 @@
+//! A scheduler for running tasks and dispatching events at a later time.
+//!
+//! The [`Timer`] service is available from any Sylphie handler using `get_service`. All tasks
+//! scheduled through it are cancelled when [`ShutdownEvent`](`crate::core::ShutdownEvent`) is
+//! dispatched, so they will not prevent the bot from shutting down.
+
+use crate::errors::*;
+use crate::watchdog::Watchdog;
+use chrono::{DateTime, Local};
+use futures::future::{AbortHandle, Abortable};
+use parking_lot::Mutex;
+use static_events::prelude_async::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::panic::Location;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::time::{delay_for, interval_at, Instant};
+
+mod cron;
+pub use cron::CronSchedule;
+
+/// Describes when a scheduled task should be run.
+#[derive(Clone, Debug)]
+pub enum Schedule {
+    /// Runs the task once, after the given delay.
+    After(Duration),
+    /// Runs the task repeatedly at a fixed rate, starting one period from now.
+    ///
+    /// If the task falls behind, missed runs are made up for immediately.
+    Every(Duration),
+    /// Runs the task repeatedly according to a cron-like schedule in local time.
+    Cron(CronSchedule),
+}
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::After(delay) => write!(f, "once after {:?}", delay),
+            Schedule::Every(period) => write!(f, "every {:?}", period),
+            Schedule::Cron(cron) => write!(f, "cron '{}'", cron),
+        }
+    }
+}
+
+struct TaskInfo {
+    schedule: Schedule,
+    location: &'static Location<'static>,
+    abort: AbortHandle,
+}
+
+struct TimerData {
+    is_shutdown: AtomicBool,
+    next_id: AtomicU64,
+    tasks: Mutex<HashMap<u64, TaskInfo>>,
+}
+
+/// A handle to a task scheduled with [`Timer`].
+///
+/// Dropping this handle does not cancel the task.
+#[derive(Clone)]
+pub struct TimerHandle {
+    id: u64,
+    timer: Weak<TimerData>,
+}
+impl TimerHandle {
+    /// Cancels the task. If the task is currently running, it is stopped at its next
+    /// suspension point.
+    pub fn cancel(&self) {
+        if let Some(timer) = self.timer.upgrade() {
+            if let Some(task) = timer.tasks.lock().remove(&self.id) {
+                task.abort.abort();
+            }
+        }
+    }
+
+    /// Returns whether the task is still scheduled.
+    pub fn is_active(&self) -> bool {
+        match self.timer.upgrade() {
+            Some(timer) => timer.tasks.lock().contains_key(&self.id),
+            None => false,
+        }
+    }
+}
+impl fmt::Debug for TimerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TimerHandle({})", self.id)
+    }
+}
+
+/// The service used to schedule tasks and events.
+#[derive(Clone)]
+pub struct Timer(Arc<TimerData>);
+impl Timer {
+    pub(crate) fn new() -> Self {
+        Timer(Arc::new(TimerData {
+            is_shutdown: AtomicBool::new(false),
+            next_id: AtomicU64::new(0),
+            tasks: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    /// Schedules an asynchronous task to be run.
+    ///
+    /// Errors returned by the task are reported, and do not stop it from being run again.
+    #[track_caller]
+    pub fn schedule<E, F, Fut>(
+        &self, target: &Handler<E>, schedule: Schedule, task: F,
+    ) -> Result<TimerHandle>
+    where
+        E: Events,
+        F: Fn(Handler<E>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let location = Location::caller();
+        if let Schedule::Every(period) = &schedule {
+            ensure!(*period > Duration::from_secs(0), "Timer periods must not be zero.");
+        }
+
+        let id = self.0.next_id.fetch_add(1, Ordering::Relaxed);
+        let (abort, registration) = AbortHandle::new_pair();
+        {
+            let mut tasks = self.0.tasks.lock();
+            ensure!(
+                !self.0.is_shutdown.load(Ordering::Relaxed),
+                "Tasks cannot be scheduled after the bot has been shut down.",
+            );
+            tasks.insert(id, TaskInfo { schedule: schedule.clone(), location, abort });
+        }
+
+        let target = target.clone();
+        let timer = Arc::downgrade(&self.0);
+        tokio::spawn(async move {
+            let run = run_schedule(target, schedule, location, task);
+            let _ = Abortable::new(run, registration).await;
+            if let Some(timer) = timer.upgrade() {
+                timer.tasks.lock().remove(&id);
+            }
+        });
+        Ok(TimerHandle { id, timer: Arc::downgrade(&self.0) })
+    }
+
+    /// Schedules an event to be dispatched.
+    ///
+    /// The event is created using the given function each time it is dispatched.
+    #[track_caller]
+    pub fn schedule_event<E, Ev, F>(
+        &self, target: &Handler<E>, schedule: Schedule, make_event: F,
+    ) -> Result<TimerHandle>
+    where
+        E: Events,
+        Ev: Event + Send + 'static,
+        F: Fn() -> Ev + Send + Sync + 'static,
+    {
+        self.schedule(target, schedule, move |target| {
+            let ev = make_event();
+            async move {
+                target.dispatch_async(ev).await;
+                Ok(())
+            }
+        })
+    }
+
+    /// Returns the number of tasks currently scheduled.
+    pub fn active_count(&self) -> usize {
+        self.0.tasks.lock().len()
+    }
+
+    /// Returns a description of every task currently scheduled.
+    pub fn describe_active(&self) -> Vec<String> {
+        let tasks = self.0.tasks.lock();
+        let mut ids: Vec<_> = tasks.keys().cloned().collect();
+        ids.sort();
+        ids.into_iter().map(|id| {
+            let task = &tasks[&id];
+            format!("timer task #{} ({}), scheduled at {}", id, task.schedule, task.location)
+        }).collect()
+    }
+
+    /// Cancels all scheduled tasks, and prevents any new ones from being scheduled.
+    pub(crate) fn shutdown(&self) {
+        let mut tasks = self.0.tasks.lock();
+        self.0.is_shutdown.store(true, Ordering::Relaxed);
+        for (_, task) in tasks.drain() {
+            task.abort.abort();
+        }
+    }
+}
+
+async fn run_schedule<E, F, Fut>(
+    target: Handler<E>, schedule: Schedule, location: &'static Location<'static>, task: F,
+) where
+    E: Events,
+    F: Fn(Handler<E>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    match schedule {
+        Schedule::After(delay) => {
+            delay_for(delay).await;
+            run_task(&target, location, &task).await;
+        }
+        Schedule::Every(period) => {
+            let mut interval = interval_at(Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                run_task(&target, location, &task).await;
+            }
+        }
+        Schedule::Cron(cron) => {
+            // tracks the last run, so an early wakeup can't cause a task to run twice
+            let mut last_run: Option<DateTime<Local>> = None;
+            loop {
+                let now = Local::now();
+                let after = match last_run {
+                    Some(last_run) if last_run > now => last_run,
+                    _ => now,
+                };
+                let next = match cron.next_after(&after) {
+                    Some(next) => next,
+                    None => {
+                        warn!("Cron schedule '{}' will never run again.", cron);
+                        return
+                    }
+                };
+                let delay = (next - Local::now()).to_std().unwrap_or(Duration::from_secs(0));
+                delay_for(delay).await;
+                last_run = Some(next);
+                run_task(&target, location, &task).await;
+            }
+        }
+    }
+}
+async fn run_task<E, F, Fut>(target: &Handler<E>, location: &'static Location<'static>, task: &F)
+where
+    E: Events,
+    F: Fn(Handler<E>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let watchdog = target.get_service::<Watchdog>();
+    let _watch = watchdog.track(format!("timer task scheduled at {}", location));
+    if let Err(e) = Error::catch_panic_async(task(target.clone())).await {
+        e.report_error();
+    }
+}
@@ -0,0 +1,185 @@
+//! A watchdog that detects command executions, event dispatches and tasks that appear to be
+//! stuck.
+//!
+//! The deadlock detection used for `parking_lot` locks cannot see futures that are waiting
+//! forever, such as on an async lock or a channel that is never sent to. Instead, the
+//! [`Watchdog`] service tracks how long each watched operation has been running for, and writes
+//! an error report listing any that run for longer than the configured threshold.
+
+use crate::interface::Interface;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::Location;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::Span;
+
+struct TaskInfo {
+    name: String,
+    span: Span,
+    location: &'static Location<'static>,
+    started: Instant,
+    is_reported: bool,
+}
+impl TaskInfo {
+    fn describe(&self, id: u64, now: Instant) -> String {
+        let mut desc = format!(
+            "#{} {}, running for {:?}, started at {}",
+            id, self.name, now.duration_since(self.started), self.location,
+        );
+        if let Some(metadata) = self.span.metadata() {
+            desc.push_str(&format!(", in span '{}' ({})", metadata.name(), metadata.target()));
+        }
+        desc
+    }
+}
+
+struct WatchdogData {
+    threshold: Duration,
+    is_shutdown: AtomicBool,
+    next_id: AtomicU64,
+    tasks: Mutex<HashMap<u64, TaskInfo>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// A guard that keeps an operation tracked by the [`Watchdog`] until it is dropped.
+#[must_use]
+pub struct WatchdogGuard {
+    id: u64,
+    watchdog: Weak<WatchdogData>,
+}
+impl Drop for WatchdogGuard {
+    fn drop(&mut self) {
+        if let Some(watchdog) = self.watchdog.upgrade() {
+            watchdog.tasks.lock().remove(&self.id);
+        }
+    }
+}
+
+/// The service used to track long-running operations.
+///
+/// Command executions, terminal commands and timer tasks are tracked automatically. Modules
+/// should use [`Watchdog::watch`] or [`Watchdog::spawn`] for any other long-running futures,
+/// such as event dispatches from connection handlers.
+#[derive(Clone)]
+pub struct Watchdog(Arc<WatchdogData>);
+impl Watchdog {
+    pub(crate) fn new(threshold: Duration) -> Self {
+        Watchdog(Arc::new(WatchdogData {
+            threshold,
+            is_shutdown: AtomicBool::new(false),
+            next_id: AtomicU64::new(0),
+            tasks: Mutex::new(HashMap::new()),
+            thread: Mutex::new(None),
+        }))
+    }
+
+    /// Starts tracking an operation, until the returned guard is dropped.
+    ///
+    /// The current span and the caller's location are recorded to help find the operation if
+    /// it becomes stuck.
+    #[track_caller]
+    pub fn track(&self, name: impl Into<String>) -> WatchdogGuard {
+        let id = self.0.next_id.fetch_add(1, Ordering::Relaxed);
+        self.0.tasks.lock().insert(id, TaskInfo {
+            name: name.into(),
+            span: Span::current(),
+            location: Location::caller(),
+            started: Instant::now(),
+            is_reported: false,
+        });
+        WatchdogGuard { id, watchdog: Arc::downgrade(&self.0) }
+    }
+
+    /// Tracks a future until it completes or is dropped.
+    #[track_caller]
+    pub fn watch<F: Future>(
+        &self, name: impl Into<String>, fut: F,
+    ) -> impl Future<Output = F::Output> {
+        let guard = self.track(name);
+        async move {
+            let _guard = guard;
+            fut.await
+        }
+    }
+
+    /// Spawns a future onto the async runtime, tracking it until it completes.
+    #[track_caller]
+    pub fn spawn<F>(&self, name: impl Into<String>, fut: F) -> tokio::task::JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        tokio::spawn(self.watch(name, fut))
+    }
+
+    /// Returns a description of every operation currently being tracked.
+    pub fn describe_active(&self) -> Vec<String> {
+        let now = Instant::now();
+        let tasks = self.0.tasks.lock();
+        let mut ids: Vec<_> = tasks.keys().cloned().collect();
+        ids.sort();
+        ids.into_iter().map(|id| tasks[&id].describe(id, now)).collect()
+    }
+
+    /// Starts the thread that checks for stuck operations.
+    pub(crate) fn start(&self, interface: &Interface) {
+        if self.0.threshold == Duration::from_secs(0) {
+            return
+        }
+
+        let data = self.0.clone();
+        let interface = interface.clone();
+        let thread = thread::Builder::new().name("watchdog thread".to_owned()).spawn(move || {
+            let mut last_check = Instant::now();
+            while !data.is_shutdown.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(100));
+                if last_check.elapsed() >= Duration::from_secs(1) {
+                    last_check = Instant::now();
+                    check_stuck(&data, &interface);
+                }
+            }
+        });
+        match thread {
+            Ok(thread) => *self.0.thread.lock() = Some(thread),
+            Err(e) => error!("Could not start watchdog thread: {}", e),
+        }
+    }
+
+    /// Stops checking for stuck operations.
+    pub(crate) fn shutdown(&self) {
+        self.0.is_shutdown.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.0.thread.lock().take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Reports any operations that have become stuck since the last check.
+fn check_stuck(data: &WatchdogData, interface: &Interface) {
+    let now = Instant::now();
+    let stuck = {
+        let mut tasks = data.tasks.lock();
+        let mut has_new = false;
+        for task in tasks.values_mut() {
+            if !task.is_reported && now.duration_since(task.started) >= data.threshold {
+                task.is_reported = true;
+                has_new = true;
+            }
+        }
+        if !has_new {
+            return
+        }
+
+        let mut ids: Vec<_> = tasks.iter()
+            .filter(|(_, task)| task.is_reported)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort();
+        ids.into_iter().map(|id| tasks[&id].describe(id, now)).collect::<Vec<_>>()
+    };
+    interface.report_stuck_tasks(data.threshold, &stuck);
+}
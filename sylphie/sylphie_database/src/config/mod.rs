@@ -324,6 +324,31 @@ impl ConfigManager {
     pub async fn get<'a, T: ConfigType>(
         &'a self, target: &'a Handler<impl Events>, scope: Scope, key: ConfigKey<T>,
     ) -> Result<T> {
+        match self.get_explicit(target, scope, key).await? {
+            Some(val) => Ok(val),
+            None => Ok((key.0.default_value)()),
+        }
+    }
+
+    /// Retrieves the value of a configuration option for the most specific scope it has been
+    /// set in, or its default value if it has not been set in any of them.
+    ///
+    /// The scopes should be in order from most to least specific.
+    pub async fn get_resolved<'a, T: ConfigType>(
+        &'a self, target: &'a Handler<impl Events>, scopes: &'a [Scope], key: ConfigKey<T>,
+    ) -> Result<T> {
+        for scope in scopes {
+            if let Some(val) = self.get_explicit(target, scope.clone(), key).await? {
+                return Ok(val)
+            }
+        }
+        Ok((key.0.default_value)())
+    }
+
+    /// Retrieves the value of a configuration option in a scope, if it has been set there.
+    pub async fn get_explicit<'a, T: ConfigType>(
+        &'a self, target: &'a Handler<impl Events>, scope: Scope, key: ConfigKey<T>,
+    ) -> Result<Option<T>> {
         let scope = ScopeId::intern(target, scope).await?;
         let val = self.cache.cached_async((scope, key.0.id), async {
             let mut conn = target.connect_db().await?;
@@ -353,11 +378,7 @@ impl ConfigManager {
                 Ok(None)
             }
         }).await?;
-        if val.is_none() {
-            Ok((key.0.default_value)())
-        } else {
-            Ok(val.unwrap().downcast_ref::<T>().unwrap().clone())
-        }
+        Ok(val.map(|x| x.downcast_ref::<T>().unwrap().clone()))
     }
 
     pub async fn set<'a, T: ConfigType>(
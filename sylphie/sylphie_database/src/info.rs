@@ -0,0 +1,87 @@
+//! Summarizes the contents of the database, for diagnostic purposes.
+
+use crate::connection::{Database, DbConnection};
+use static_events::prelude_async::*;
+use sylphie_core::errors::*;
+
+/// The current version of a migration set.
+#[derive(Clone, Debug)]
+pub struct MigrationInfo {
+    /// The internal name of the migration set.
+    pub migration_name: String,
+    /// The schema version the migration set is currently at.
+    pub current_version: u32,
+    /// Whether this migration set is for the transient database.
+    pub is_transient: bool,
+}
+
+/// Information about a table used by a KVS store.
+#[derive(Clone, Debug)]
+pub struct KvsTableInfo {
+    /// The module path of the store that owns this table.
+    pub module_path: String,
+    /// The name of the table in the database.
+    pub table_name: String,
+    /// The version of the KVS schema used by the table.
+    pub schema_version: u32,
+    /// Whether this table is stored in the transient database.
+    pub is_transient: bool,
+}
+
+/// A summary of the contents of the database.
+#[derive(Clone, Debug)]
+pub struct DatabaseInfo {
+    /// The migration sets that have been applied to the database.
+    pub migrations: Vec<MigrationInfo>,
+    /// The tables used by KVS stores.
+    pub kvs_tables: Vec<KvsTableInfo>,
+}
+
+fn prefix(is_transient: bool) -> &'static str {
+    if is_transient { "transient." } else { "" }
+}
+
+async fn query_migrations(
+    conn: &mut DbConnection, is_transient: bool, out: &mut Vec<MigrationInfo>,
+) -> Result<()> {
+    let rows: Vec<(String, u32)> = conn.query_vec_nullary(format!(
+        "SELECT migration_name, current_version FROM {}sylphie_db_migrations_tracking \
+         ORDER BY migration_name",
+        prefix(is_transient),
+    )).await?;
+    for (migration_name, current_version) in rows {
+        out.push(MigrationInfo { migration_name, current_version, is_transient });
+    }
+    Ok(())
+}
+
+async fn query_kvs_tables(
+    conn: &mut DbConnection, is_transient: bool, out: &mut Vec<KvsTableInfo>,
+) -> Result<()> {
+    let rows: Vec<(String, String, u32)> = conn.query_vec_nullary(format!(
+        "SELECT module_path, table_name, kvs_schema_version FROM {}sylphie_db_kvs_info \
+         ORDER BY module_path",
+        prefix(is_transient),
+    )).await?;
+    for (module_path, table_name, schema_version) in rows {
+        out.push(KvsTableInfo { module_path, table_name, schema_version, is_transient });
+    }
+    Ok(())
+}
+
+/// Returns a summary of the contents of the database.
+///
+/// This may only be called after the database has been initialized.
+pub async fn database_info(target: &Handler<impl Events>) -> Result<DatabaseInfo> {
+    let mut conn = target.get_service::<Database>().connect().await?;
+
+    let mut migrations = Vec::new();
+    query_migrations(&mut conn, false, &mut migrations).await?;
+    query_migrations(&mut conn, true, &mut migrations).await?;
+
+    let mut kvs_tables = Vec::new();
+    query_kvs_tables(&mut conn, false, &mut kvs_tables).await?;
+    query_kvs_tables(&mut conn, true, &mut kvs_tables).await?;
+
+    Ok(DatabaseInfo { migrations, kvs_tables })
+}
@@ -0,0 +1,486 @@
+use arc_swap::*;
+use chrono::{DateTime, TimeZone, Utc};
+use crate::connection::*;
+use crate::interner::*;
+use crate::migrations::*;
+use crate::serializable::*;
+use parking_lot::Mutex;
+use serde::*;
+use static_events::prelude_async::*;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+use sylphie_core::core::InitEvent;
+use sylphie_core::derives::*;
+use sylphie_core::module::ModuleId;
+use sylphie_core::prelude::*;
+use sylphie_core::timer::{Schedule, Timer, TimerHandle};
+
+/// Decides what happens to jobs that became due while the bot was not running.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum CatchUpPolicy {
+    /// Every missed run is fired.
+    ///
+    /// For repeating jobs, the job is fired once for every interval that was missed.
+    RunAll,
+    /// Only the most recent missed run is fired.
+    ///
+    /// For repeating jobs, the job is fired once. Jobs that only run once are fired as with
+    /// [`CatchUpPolicy::RunAll`].
+    RunLatest,
+    /// Missed runs are discarded.
+    ///
+    /// Repeating jobs are rescheduled for their next run in the future.
+    Skip,
+}
+impl CatchUpPolicy {
+    fn to_id(self) -> u32 {
+        match self {
+            CatchUpPolicy::RunAll => 0,
+            CatchUpPolicy::RunLatest => 1,
+            CatchUpPolicy::Skip => 2,
+        }
+    }
+    fn from_id(id: u32) -> Result<Self> {
+        Ok(match id {
+            0 => CatchUpPolicy::RunAll,
+            1 => CatchUpPolicy::RunLatest,
+            2 => CatchUpPolicy::Skip,
+            _ => bail!("Unknown catch-up policy id: {}", id),
+        })
+    }
+}
+
+/// Identifies a job stored in a [`JobStore`].
+#[derive(Serialize, Deserialize)]
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+#[serde(transparent)]
+pub struct JobId(i64);
+impl JobId {
+    pub fn as_i64(&self) -> i64 {
+        self.0
+    }
+}
+
+/// Dispatched when a job in a [`JobStore`] is due.
+///
+/// As every store with the same payload type dispatches the same event type, handlers should
+/// use [`ScheduledJobEvent::is_from`] to check which store the job belongs to.
+pub struct ScheduledJobEvent<T: DbSerializable> {
+    store_id: ModuleId,
+    /// The id of the job that is being run.
+    pub job_id: JobId,
+    /// The payload the job was scheduled with.
+    pub payload: T,
+    /// The time the job was scheduled to run at.
+    ///
+    /// This may be significantly in the past if the job was missed while the bot was not running.
+    pub scheduled_for: DateTime<Utc>,
+}
+failable_event!([T: DbSerializable] ScheduledJobEvent<T>, (), Error);
+impl <T: DbSerializable> ScheduledJobEvent<T> {
+    /// Returns whether this event was dispatched by the given store.
+    pub fn is_from(&self, store: &JobStore<T>) -> bool {
+        self.store_id == store.info.id()
+    }
+}
+
+static JOBS_MIGRATIONS: MigrationData = MigrationData {
+    migration_id: "jobs e0d6a1c3-7b4c-4b0e-9a52-3f1d8c6e2b90",
+    migration_set_name: "jobs",
+    is_transient: false,
+    target_version: 1,
+    scripts: &[
+        migration_script!(0, 1, "sql/jobs_0_to_1.sql"),
+    ],
+};
+pub(crate) async fn init_jobs(target: &Handler<impl Events>) -> Result<()> {
+    JOBS_MIGRATIONS.execute(target).await?;
+    Ok(())
+}
+
+/// The longest interval or delay a job may be scheduled with.
+///
+/// This keeps the times jobs are rearmed at far from the limits of the timestamps they are
+/// stored as.
+const MAX_JOB_DELAY: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+
+fn millis_to_time(millis: i64) -> Result<DateTime<Utc>> {
+    Utc.timestamp_millis_opt(millis).single().internal_err(|| "Job time is out of range.")
+}
+fn duration_to_millis(duration: Duration) -> Result<i64> {
+    let millis = duration.as_millis();
+    ensure!(millis > 0, "Job intervals must be at least one millisecond.");
+    ensure!(duration <= MAX_JOB_DELAY, "Job interval is too long.");
+    Ok(millis as i64)
+}
+
+/// What happens to a job when a store is loaded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum LoadAction {
+    /// The job is armed to fire at the given time.
+    Arm(i64),
+    /// The job is deleted without being run.
+    Delete,
+}
+
+/// Decides what happens to a job when a store is loaded, and how many of its runs are skipped.
+///
+/// Returns `None` if the job's times are out of range.
+fn plan_load(
+    now: i64, fire_at: i64, repeat_interval: Option<i64>, policy: CatchUpPolicy,
+) -> Option<(LoadAction, i64)> {
+    if fire_at > now {
+        return Some((LoadAction::Arm(fire_at), 0))
+    }
+    Some(match (repeat_interval, policy) {
+        // repeating jobs catch up by rearming themselves with overdue times
+        (_, CatchUpPolicy::RunAll) | (None, CatchUpPolicy::RunLatest) =>
+            (LoadAction::Arm(fire_at), 0),
+        (None, CatchUpPolicy::Skip) => (LoadAction::Delete, 1),
+        (Some(interval), _) => {
+            let overdue = now.checked_sub(fire_at)?;
+            let missed = overdue.checked_div(interval)? + 1;
+            // `(missed - 1) * interval` is at most `overdue`, so this can't overflow
+            let latest = fire_at + (missed - 1) * interval;
+            if policy == CatchUpPolicy::Skip {
+                (LoadAction::Arm(latest.checked_add(interval)?), missed)
+            } else {
+                (LoadAction::Arm(latest), missed - 1)
+            }
+        }
+    })
+}
+
+type JobRow = (i64, i64, Option<i64>, u32, SerializeValue, StringId, u32);
+
+struct JobStoreData {
+    db: Database,
+    interner: InternerLock,
+    store_id: ModuleId,
+    module_path: Arc<str>,
+    payload_id: StringId,
+    timers: Mutex<HashMap<JobId, TimerHandle>>,
+}
+impl JobStoreData {
+    async fn load_payload<T: DbSerializable>(
+        &self, conn: &mut DbConnection,
+        value: SerializeValue, schema_id: StringId, schema_ver: u32,
+    ) -> Result<T> {
+        if schema_id == self.payload_id && schema_ver == T::SCHEMA_VERSION {
+            T::Format::deserialize(value)
+        } else {
+            let schema_name = self.interner.get_str_id_rev(conn, schema_id).await?;
+            if T::can_migrate_from(&schema_name, schema_ver) {
+                T::do_migration(&schema_name, schema_ver, value)
+            } else {
+                bail!(
+                    "Could not migrate job payload to current schema version! ({}:{} -> {}:{})",
+                    schema_name, schema_ver, T::ID, T::SCHEMA_VERSION,
+                );
+            }
+        }
+    }
+
+    async fn set_fire_at(&self, conn: &mut DbConnection, job: JobId, time: i64) -> Result<()> {
+        conn.execute(
+            "UPDATE sylphie_db_scheduled_jobs SET fire_at = ? WHERE job_id = ?",
+            (time, job.0),
+        ).await?;
+        Ok(())
+    }
+    async fn delete(&self, conn: &mut DbConnection, job: JobId) -> Result<()> {
+        conn.execute("DELETE FROM sylphie_db_scheduled_jobs WHERE job_id = ?", job.0).await?;
+        Ok(())
+    }
+
+    fn arm<T: DbSerializable, E: Events>(
+        self: &Arc<Self>, target: &Handler<E>, job: JobId, fire_at: i64,
+    ) -> Result<()> {
+        let delay = (millis_to_time(fire_at)? - Utc::now()).to_std()
+            .unwrap_or(Duration::from_secs(0));
+        let data = self.clone();
+        let handle = target.get_service::<Timer>().schedule(
+            target, Schedule::After(delay), move |target| {
+                let data = data.clone();
+                async move { data.fire::<T, E>(&target, job, fire_at).await }
+            },
+        )?;
+        self.timers.lock().insert(job, handle);
+        Ok(())
+    }
+
+    async fn fire<T: DbSerializable, E: Events>(
+        self: Arc<Self>, target: &Handler<E>, job: JobId, fire_at: i64,
+    ) -> Result<()> {
+        self.timers.lock().remove(&job);
+
+        let mut conn = self.db.connect().await?;
+        let row: Option<(Option<i64>, SerializeValue, StringId, u32)> = conn.query_row(
+            "SELECT repeat_interval, payload, payload_schema_id, payload_schema_ver \
+             FROM sylphie_db_scheduled_jobs WHERE job_id = ?",
+            job.0,
+        ).await?;
+        let (repeat_interval, value, schema_id, schema_ver) = match row {
+            Some(row) => row,
+            None => return Ok(()), // the job was cancelled
+        };
+        let payload = self.load_payload::<T>(&mut conn, value, schema_id, schema_ver).await;
+
+        // The database is updated before the event is dispatched, so a crash during a job
+        // can't cause it to be run again. This is done even if the payload could not be
+        // loaded, so repeating jobs are not left stuck in the past.
+        match repeat_interval {
+            Some(interval) => {
+                let next = fire_at.checked_add(interval)
+                    .internal_err(|| "Job time is out of range.")?;
+                self.set_fire_at(&mut conn, job, next).await?;
+                self.arm::<T, E>(target, job, next)?;
+            }
+            None => self.delete(&mut conn, job).await?,
+        }
+        std::mem::drop(conn);
+
+        target.dispatch_async(ScheduledJobEvent {
+            store_id: self.store_id,
+            job_id: job,
+            payload: payload?,
+            scheduled_for: millis_to_time(fire_at)?,
+        }).await
+    }
+
+    async fn load_jobs<T: DbSerializable, E: Events>(
+        self: &Arc<Self>, target: &Handler<E>,
+    ) -> Result<()> {
+        let mut conn = self.db.connect().await?;
+        let rows: Vec<(i64, i64, Option<i64>, u32)> = conn.query_vec(
+            "SELECT job_id, fire_at, repeat_interval, catch_up_policy \
+             FROM sylphie_db_scheduled_jobs WHERE module_path = ? ORDER BY fire_at",
+            self.module_path.to_string(),
+        ).await?;
+
+        let now = Utc::now().timestamp_millis();
+        let mut skipped = 0;
+        for (job_id, fire_at, repeat_interval, policy) in rows {
+            let job = JobId(job_id);
+            if self.timers.lock().contains_key(&job) {
+                continue // the job was scheduled by another `InitEvent` handler
+            }
+            let policy = CatchUpPolicy::from_id(policy)?;
+
+            let (action, job_skipped) = plan_load(now, fire_at, repeat_interval, policy)
+                .internal_err(|| "Job time is out of range.")?;
+            skipped += job_skipped;
+            match action {
+                LoadAction::Arm(next) => {
+                    if next != fire_at {
+                        self.set_fire_at(&mut conn, job, next).await?;
+                    }
+                    self.arm::<T, E>(target, job, next)?;
+                }
+                LoadAction::Delete => self.delete(&mut conn, job).await?,
+            }
+        }
+        if skipped != 0 {
+            debug!("Skipped {} missed job runs for '{}'.", skipped, self.module_path);
+        }
+
+        Ok(())
+    }
+}
+
+/// A store for jobs that should be run at a later time, even across restarts of the bot.
+///
+/// Each job carries a payload, and dispatches a [`ScheduledJobEvent`] when it is due. Jobs are
+/// keyed by the module path of the store, so renaming the module will orphan its jobs.
+///
+/// Stored jobs are loaded once [`InitEvent`] is dispatched, so they are never run by
+/// maintenance tasks such as migrations.
+///
+/// This is a module, and should be used by attaching it to the your module as a submodule.
+#[derive(Module)]
+#[module(component, requires(Database, Interner))]
+pub struct JobStore<T: DbSerializable> {
+    #[module_info] info: ModuleInfo,
+    data: ArcSwapOption<JobStoreData>,
+    phantom: PhantomData<fn(T)>,
+}
+#[module_impl]
+impl <T: DbSerializable> JobStore<T> {
+    #[event_handler]
+    async fn init_jobs(&self, target: &Handler<impl Events>, _: &crate::InitDbEvent) -> Result<()> {
+        let data = Arc::new(JobStoreData {
+            db: target.get_service::<Database>().clone(),
+            interner: target.get_service::<Interner>().lock(),
+            store_id: self.info.id(),
+            module_path: self.info.arc_name(),
+            payload_id: StringId::intern(target, T::ID).await?,
+            timers: Mutex::new(HashMap::new()),
+        });
+        self.data.store(Some(data));
+        Ok(())
+    }
+
+    #[event_handler]
+    async fn load_jobs(&self, target: &Handler<impl Events>, _: &InitEvent) -> Result<()> {
+        self.load_data().load_jobs::<T, _>(target).await
+    }
+
+    fn load_data(&self) -> Arc<JobStoreData> {
+        self.data.load().as_ref().expect("JobStore not yet initialized.").clone()
+    }
+
+    async fn insert(
+        &self, target: &Handler<impl Events>,
+        fire_at: i64, repeat_interval: Option<i64>, payload: T, policy: CatchUpPolicy,
+    ) -> Result<JobId> {
+        let data = self.load_data();
+        let mut conn = data.db.connect().await?;
+        let mut transaction = conn.transaction().await?;
+        transaction.execute(
+            "INSERT INTO sylphie_db_scheduled_jobs \
+                 (module_path, fire_at, repeat_interval, catch_up_policy, \
+                  payload, payload_schema_id, payload_schema_ver) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            (
+                data.module_path.to_string(), fire_at, repeat_interval, policy.to_id(),
+                T::Format::serialize(&payload)?, data.payload_id, T::SCHEMA_VERSION,
+            ),
+        ).await?;
+        let job_id: Option<i64> =
+            transaction.query_row_nullary("SELECT last_insert_rowid()").await?;
+        transaction.commit().await?;
+
+        let job = JobId(job_id.internal_err(|| "Could not retrieve id of new job.")?);
+        data.arm::<T, _>(target, job, fire_at)?;
+        Ok(job)
+    }
+
+    /// Schedules a job to be run once at a given time.
+    pub async fn schedule_at(
+        &self, target: &Handler<impl Events>,
+        time: DateTime<Utc>, payload: T, policy: CatchUpPolicy,
+    ) -> Result<JobId> {
+        self.insert(target, time.timestamp_millis(), None, payload, policy).await
+    }
+
+    /// Schedules a job to be run once after a given delay.
+    pub async fn schedule_after(
+        &self, target: &Handler<impl Events>,
+        delay: Duration, payload: T, policy: CatchUpPolicy,
+    ) -> Result<JobId> {
+        ensure!(delay <= MAX_JOB_DELAY, "Job delay is too long.");
+        let delay = chrono::Duration::from_std(delay)
+            .internal_err(|| "Job delay is too long.")?;
+        let time = Utc::now().checked_add_signed(delay)
+            .internal_err(|| "Job delay is too long.")?;
+        self.schedule_at(target, time, payload, policy).await
+    }
+
+    /// Schedules a job to be run repeatedly, starting at a given time.
+    pub async fn schedule_repeating(
+        &self, target: &Handler<impl Events>,
+        first: DateTime<Utc>, interval: Duration, payload: T, policy: CatchUpPolicy,
+    ) -> Result<JobId> {
+        let interval = duration_to_millis(interval)?;
+        self.insert(target, first.timestamp_millis(), Some(interval), payload, policy).await
+    }
+
+    /// Cancels a job. Returns `false` if the job did not exist.
+    pub async fn cancel(&self, job: JobId) -> Result<bool> {
+        let data = self.load_data();
+        if let Some(handle) = data.timers.lock().remove(&job) {
+            handle.cancel();
+        }
+        let deleted = data.db.connect().await?.execute(
+            "DELETE FROM sylphie_db_scheduled_jobs WHERE job_id = ? AND module_path = ?",
+            (job.0, data.module_path.to_string()),
+        ).await?;
+        Ok(deleted != 0)
+    }
+
+    /// Returns all jobs pending in this store, along with the time they are next due.
+    pub async fn pending_jobs(&self) -> Result<Vec<(JobId, DateTime<Utc>, T)>> {
+        let data = self.load_data();
+        let mut conn = data.db.connect().await?;
+        let rows: Vec<JobRow> = conn.query_vec(
+            "SELECT job_id, fire_at, repeat_interval, catch_up_policy, \
+                    payload, payload_schema_id, payload_schema_ver \
+             FROM sylphie_db_scheduled_jobs WHERE module_path = ? ORDER BY fire_at",
+            data.module_path.to_string(),
+        ).await?;
+
+        let mut jobs = Vec::new();
+        for (job_id, fire_at, _, _, value, schema_id, schema_ver) in rows {
+            let payload = data.load_payload(&mut conn, value, schema_id, schema_ver).await?;
+            jobs.push((JobId(job_id), millis_to_time(fire_at)?, payload));
+        }
+        Ok(jobs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 100_000;
+
+    fn arm(fire_at: i64, skipped: i64) -> Option<(LoadAction, i64)> {
+        Some((LoadAction::Arm(fire_at), skipped))
+    }
+
+    #[test]
+    fn pending_test() {
+        for &policy in &[CatchUpPolicy::RunAll, CatchUpPolicy::RunLatest, CatchUpPolicy::Skip] {
+            assert_eq!(plan_load(NOW, NOW + 1, None, policy), arm(NOW + 1, 0));
+            assert_eq!(plan_load(NOW, NOW + 1, Some(10), policy), arm(NOW + 1, 0));
+        }
+    }
+
+    #[test]
+    fn run_all_test() {
+        let policy = CatchUpPolicy::RunAll;
+        assert_eq!(plan_load(NOW, NOW - 500, None, policy), arm(NOW - 500, 0));
+        assert_eq!(plan_load(NOW, NOW - 25, Some(10), policy), arm(NOW - 25, 0));
+    }
+
+    #[test]
+    fn run_latest_test() {
+        let policy = CatchUpPolicy::RunLatest;
+        assert_eq!(plan_load(NOW, NOW - 500, None, policy), arm(NOW - 500, 0));
+        assert_eq!(plan_load(NOW, NOW - 25, Some(10), policy), arm(NOW - 5, 2));
+        assert_eq!(plan_load(NOW, NOW - 20, Some(10), policy), arm(NOW, 2));
+        assert_eq!(plan_load(NOW, NOW, Some(10), policy), arm(NOW, 0));
+    }
+
+    #[test]
+    fn skip_test() {
+        let policy = CatchUpPolicy::Skip;
+        assert_eq!(plan_load(NOW, NOW - 500, None, policy), Some((LoadAction::Delete, 1)));
+        assert_eq!(plan_load(NOW, NOW - 25, Some(10), policy), arm(NOW + 5, 3));
+        assert_eq!(plan_load(NOW, NOW - 20, Some(10), policy), arm(NOW + 10, 3));
+        assert_eq!(plan_load(NOW, NOW, Some(10), policy), arm(NOW + 10, 1));
+    }
+
+    #[test]
+    fn huge_interval_test() {
+        let max = i64::max_value();
+        assert_eq!(plan_load(NOW, NOW - 25, Some(max), CatchUpPolicy::RunAll), arm(NOW - 25, 0));
+        assert_eq!(plan_load(NOW, NOW - 25, Some(max), CatchUpPolicy::RunLatest), arm(NOW - 25, 0));
+        assert_eq!(plan_load(NOW, NOW - 25, Some(max), CatchUpPolicy::Skip), None);
+        assert_eq!(plan_load(NOW, NOW - 25, Some(0), CatchUpPolicy::Skip), None);
+        assert_eq!(plan_load(NOW, i64::min_value(), Some(10), CatchUpPolicy::RunLatest), None);
+        assert_eq!(plan_load(max, max - 5, Some(10), CatchUpPolicy::Skip), None);
+    }
+
+    #[test]
+    fn interval_test() {
+        assert_eq!(duration_to_millis(Duration::from_millis(10)).unwrap(), 10);
+        assert!(duration_to_millis(MAX_JOB_DELAY).is_ok());
+        assert!(duration_to_millis(Duration::from_millis(0)).is_err());
+        assert!(duration_to_millis(MAX_JOB_DELAY + Duration::from_millis(1)).is_err());
+        assert!(duration_to_millis(Duration::from_secs(u64::max_value())).is_err());
+        assert!(millis_to_time(i64::max_value()).is_err());
+    }
+}
@@ -383,7 +383,7 @@ impl KvsStoreQueries {
 /// You should generally prefer [`KvsStore`] or [`TransientKvsStore`] as convenience wrappers
 /// over this type.
 #[derive(Module)]
-#[module(component)]
+#[module(component, requires(Database, Interner))]
 pub struct BaseKvsStore<K: DbSerializable + Hash + Eq, V: DbSerializable, T: KvsType> {
     #[module_info] info: ModuleInfo,
     data: ArcSwapOption<BaseKvsStoreInfo>,
@@ -7,6 +7,8 @@ pub mod migrations; // this goes early because there are macros we use in here
 pub mod config;
 mod interner;
 pub mod connection;
+pub mod info;
+pub mod jobs;
 pub mod kvs;
 pub mod serializable;
 pub mod singleton;
@@ -53,6 +55,9 @@ impl InnerHandler {
 /// This should be a part of the module tree for database connections and migrations to work
 /// correctly.
 #[derive(Module)]
+#[module(provides(
+    config::ConfigManager, interner::Interner, connection::Database, migrations::MigrationManager,
+))]
 pub struct DatabaseModule {
     #[module_info] info: ModuleInfo,
     #[subhandler] #[init_with { InnerHandler::new() }] inner: InnerHandler,
@@ -82,8 +87,7 @@ impl DatabaseModule {
     fn init_db_paths(&self, target: &Handler<impl Events>) -> Result<()> {
         let info = target.get_service::<BotInfo>();
 
-        let mut db_path = info.root_path().to_owned();
-        db_path.push("db");
+        let db_path = info.data_path().to_owned();
         fs::create_dir_all(&db_path)?;
 
         let mut persistent_path = db_path.to_owned();
@@ -99,6 +103,7 @@ impl DatabaseModule {
     async fn init_serializers(&self, target: &Handler<impl Events>) -> Result<()> {
         crate::interner::init_interner(target).await?;
         crate::kvs::init_kvs(target).await?;
+        crate::jobs::init_jobs(target).await?;
         crate::config::init_config(target).await?;
         Ok(())
     }
@@ -106,5 +111,6 @@ impl DatabaseModule {
     #[event_handler]
     fn setup_logger(ev: &mut SetupLoggerEvent) {
         ev.add_console_directive("sylphie_database=debug");
+        ev.add_file_directive("sylphie_database=debug");
     }
 }
\ No newline at end of file
@@ -42,7 +42,7 @@ struct InitData {
 /// You should generally prefer [`KvsStore`] or [`TransientKvsStore`] as convenience wrappers
 /// over this type.
 #[derive(Module)]
-#[module(component)]
+#[module(component, requires(SingletonDataStore))]
 pub struct SingletonStore<V: Default + DbSerializable> {
     #[module_info] info: ModuleInfo,
     cached_instance: RwLock<Option<V>>,
@@ -0,0 +1,11 @@
+CREATE TABLE sylphie_db_scheduled_jobs (
+    job_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    module_path TEXT NOT NULL,
+    fire_at BIGINT NOT NULL, -- In milliseconds since the Unix epoch.
+    repeat_interval BIGINT, -- In milliseconds, or NULL for jobs that only run once.
+    catch_up_policy INTEGER NOT NULL,
+    payload BLOB NOT NULL,
+    payload_schema_id INTEGER NOT NULL,
+    payload_schema_ver INTEGER NOT NULL
+);
+CREATE INDEX sylphie_db_scheduled_jobs_by_module ON sylphie_db_scheduled_jobs (module_path);
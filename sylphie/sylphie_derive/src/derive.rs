@@ -13,6 +13,7 @@ use quote::*;
 struct FieldAttrs {
     is_module_info: bool,
     is_submodule: bool,
+    is_service: bool,
     init_with: Option<Expr>,
 }
 impl FieldAttrs {
@@ -31,6 +32,10 @@ impl FieldAttrs {
                     tp.is_submodule = true;
                     exclusive_count += 1;
                 },
+                "service" => {
+                    tp.is_service = true;
+                    set_span = false;
+                }
                 "init_with" => {
                     if tp.init_with.is_some() {
                         error(attr.span(), "Only one #[init_with] attribute can be used.")?;
@@ -67,6 +72,10 @@ struct ModuleAttrs {
     anonymous: bool,
     #[darling(default)]
     component: bool,
+    #[darling(default)]
+    requires: util::PathList,
+    #[darling(default)]
+    provides: util::PathList,
 }
 
 fn git_metadata(paths: &CratePaths) -> std::result::Result<SynTokenStream, GitError> {
@@ -148,6 +157,16 @@ fn derive_module(
     let mut fields = Vec::new();
     let mut info_field = None;
     let mut field_checks = Vec::new();
+    let mut dependencies = Vec::new();
+    for path in attrs.requires.iter() {
+        dependencies.push(quote! { __mod_walker.require_service::<#path>(__mod_parent); });
+    }
+    for path in attrs.provides.iter() {
+        dependencies.push(quote! { __mod_walker.provide_service::<#path>(); });
+    }
+    if input.attrs.iter().any(|x| last_path_segment(&x.path) == "service") {
+        dependencies.push(quote! { __mod_walker.provide_service::<Self>(); });
+    }
     for field in &mut data.fields {
         let attrs = FieldAttrs::from_attrs(&field.attrs)?;
         let name = &field.ident;
@@ -160,6 +179,10 @@ fn derive_module(
             info_field = Some(&field.ident);
         }
 
+        if attrs.is_service {
+            dependencies.push(quote! { __mod_walker.provide_service::<#ty>(); });
+        }
+
         field_names.push(field.ident.clone().unwrap());
         if let Some(init_with) = attrs.init_with {
             fields.push(quote! { #init_with });
@@ -217,6 +240,7 @@ fn derive_module(
                 __mod_walker: &mut #core::module::ModuleTreeWalker,
             ) -> Self {
                 #(#field_checks)*
+                #(#dependencies)*
                 #ident {
                     #(#field_names: #fields,)*
                 }
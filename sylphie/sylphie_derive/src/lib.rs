@@ -9,6 +9,7 @@ use quote::*;
 
 mod derive;
 mod module_impl;
+mod parse_arg;
 
 pub(crate) struct CratePaths {
     core: SynTokenStream,
@@ -57,6 +58,24 @@ pub fn derive_module_core_internal(input: TokenStream) -> TokenStream {
     try_syn!(derive::derive_events(&crate_paths_for_core_internal(), input))
 }
 
+#[proc_macro_derive(SylphieParseArg)]
+pub fn derive_parse_arg_sylphie(input: TokenStream) -> TokenStream {
+    try_syn!(parse_arg::derive_parse_arg(&crate_paths_for_sylphie(), input))
+}
+#[proc_macro_derive(CoreParseArg)]
+pub fn derive_parse_arg_core(input: TokenStream) -> TokenStream {
+    try_syn!(parse_arg::derive_parse_arg(&crate_paths_for_core(), input))
+}
+
+#[proc_macro_derive(SylphieParseFlags, attributes(flag))]
+pub fn derive_parse_flags_sylphie(input: TokenStream) -> TokenStream {
+    try_syn!(parse_arg::derive_parse_flags(&crate_paths_for_sylphie(), input))
+}
+#[proc_macro_derive(CoreParseFlags, attributes(flag))]
+pub fn derive_parse_flags_core(input: TokenStream) -> TokenStream {
+    try_syn!(parse_arg::derive_parse_flags(&crate_paths_for_core(), input))
+}
+
 #[proc_macro_attribute]
 pub fn module_impl_sylphie(_: TokenStream, item: TokenStream) -> TokenStream {
     try_syn!(module_impl::derive_impl(&crate_paths_for_sylphie(), item))
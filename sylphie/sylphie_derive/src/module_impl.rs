@@ -11,6 +11,8 @@ use quote::*;
 struct CommandAttrs {
     #[darling(default)]
     name: Option<String>,
+    #[darling(default)]
+    permission: Option<String>,
 }
 
 #[derive(FromMeta, Debug, Default)]
@@ -105,13 +107,16 @@ fn create_command_handler(
             &name_str
         }
     });
-    let command_info = quote! { #commands::commands::CommandInfo::new(#cmd_name) };
+    let mut command_info = quote! { #commands::commands::CommandInfo::new(#cmd_name) };
+    if let Some(permission) = &attrs.permission {
+        command_info = quote! { #command_info.permission(#permission) };
+    }
 
     // TODO: Support commands without a self parameter.
     let ev_call = &method.sig.ident;
     let mut ev_call_params = Vec::new();
-    for _ in 1..method.sig.inputs.len() {
-        ev_call_params.push(quote! { _ctx.next_arg()? })
+    for i in 1..method.sig.inputs.len() {
+        ev_call_params.push(ident!("_arg_{}", i));
     }
 
     let cmd_marker = ident!("ModuleImpl_CommandMarker_{}", ev_call);
@@ -125,6 +130,8 @@ fn create_command_handler(
         async fn #cmd_impl(
             &self, mut _ctx: #commands::args::ArgsParserCtx<'_, impl #static_events::Events>,
         ) -> #core::errors::Result<()> {
+            #(let #ev_call_params = _ctx.next_arg()?;)*
+            _ctx.finish()?;
             self.#ev_call(#(#ev_call_params,)*).await
         }
     })?;
@@ -287,8 +294,79 @@ fn process_items(
     }
 }
 
+fn is_handler_type(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(ty) => match &*ty.elem {
+            Type::Path(path) => last_path_segment(&path.path) == "Handler",
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Makes sure a parameter binds an identifier, so it can be referred to by generated code.
+fn param_ident(param: &mut PatType, default: &str) -> Ident {
+    match &*param.pat {
+        Pat::Ident(pat) => pat.ident.clone(),
+        _ => {
+            let ident = ident!("{}", default);
+            *param.pat = parse_quote!(#ident);
+            ident
+        }
+    }
+}
+
+/// Skips an event handler when its module should not receive an event, as decided by
+/// `FilteredEvent`.
+fn filter_event_handler(paths: &CratePaths, method: &mut ImplItemMethod) {
+    let core = &paths.core;
+    let static_events = quote! { #core::__macro_export::static_events::prelude_async };
+
+    // handlers without a `self` parameter do not belong to a particular module
+    match method.sig.inputs.first() {
+        Some(FnArg::Receiver(_)) => { }
+        _ => return,
+    }
+    let has_target = match method.sig.inputs.iter().nth(1) {
+        Some(FnArg::Typed(param)) => is_handler_type(&param.ty),
+        _ => false,
+    };
+    if !has_target {
+        method.sig.inputs.insert(1, parse_quote! {
+            __module_impl_target: &#static_events::Handler<impl #static_events::Events>
+        });
+    }
+    let mut params = method.sig.inputs.iter_mut().skip(1);
+    let target = match params.next() {
+        Some(FnArg::Typed(param)) => param_ident(param, "__module_impl_target"),
+        _ => return,
+    };
+    let ev = match params.next() {
+        Some(FnArg::Typed(param)) => param_ident(param, "__module_impl_ev"),
+        _ => return, // let static-events report the error
+    };
+
+    let check: Stmt = parse_quote! {
+        if !#core::__macro_priv::is_handler_enabled(self, #target, &*#ev) {
+            return #core::__macro_priv::SkippedHandler::skipped()
+        }
+    };
+    method.block.stmts.insert(0, check);
+}
+
+fn filter_event_handlers(paths: &CratePaths, input: &mut ItemImpl) {
+    for item in &mut input.items {
+        if let ImplItem::Method(method) = item {
+            if method.attrs.iter().any(|x| last_path_segment(&x.path) == "event_handler") {
+                filter_event_handler(paths, method);
+            }
+        }
+    }
+}
+
 pub(crate) fn derive_impl(paths: &CratePaths, input: TokenStream) -> Result<TokenStream> {
     let mut input: ItemImpl = parse(input)?;
+    filter_event_handlers(paths, &mut input);
 
     let core = &paths.core;
     let mut events = EventsImplAttr::new(
@@ -0,0 +1,155 @@
+use crate::CratePaths;
+use darling::*;
+use proc_macro::TokenStream;
+use static_events_internals::{*, Result};
+use static_events_internals::utils::*;
+use syn::*;
+use syn::spanned::Spanned;
+use quote::*;
+
+/// Converts a variant name such as `DefaultAllow` into the form shown to users, `default-allow`.
+fn display_name(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.trim_start_matches("r#").chars() {
+        if c == '_' || (c.is_uppercase() && !out.is_empty() && !out.ends_with('-')) {
+            out.push('-');
+        }
+        if c != '_' {
+            out.extend(c.to_lowercase());
+        }
+    }
+    out.trim_matches('-').to_string()
+}
+
+/// Normalizes a variant name in the same way as `__macro_priv::normalize_enum_arg`, so it can be
+/// matched against the normalized user input.
+fn match_name(name: &str) -> String {
+    name.trim_start_matches("r#")
+        .chars().filter(|x| *x != '-' && *x != '_').flat_map(char::to_lowercase).collect()
+}
+
+pub(crate) fn derive_parse_arg(paths: &CratePaths, input: TokenStream) -> Result<TokenStream> {
+    let input: DeriveInput = parse(input)?;
+    let core = &paths.core;
+    let commands = &paths.commands;
+    let static_events = quote! { #core::__macro_export::static_events::prelude_async };
+
+    if !input.generics.params.is_empty() {
+        error(input.generics.span(), "#[derive(ParseArg)] does not support generic types.")?;
+    }
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => error(input.span(), "#[derive(ParseArg)] may only be used with enums.")?,
+    };
+
+    let name = &input.ident;
+    let mut names = Vec::new();
+    let mut match_names = Vec::new();
+    let mut variants = Vec::new();
+    for variant in &data.variants {
+        if !variant.fields.is_empty() {
+            error(variant.span(), "#[derive(ParseArg)] only supports variants without fields.")?;
+        }
+        let variant_name = variant.ident.to_string();
+        let match_name = match_name(&variant_name);
+        if match_names.contains(&match_name) {
+            error(
+                variant.span(),
+                "#[derive(ParseArg)] variant names must differ by more than case and underscores.",
+            )?;
+        }
+        match_names.push(match_name);
+        names.push(display_name(&variant_name));
+        variants.push(&variant.ident);
+    }
+    let expected = format!("one of: {}", names.join(", "));
+
+    Ok((quote! {
+        impl <'a, E: #static_events::Events> #commands::args::ParseArg<'a, E> for #name {
+            fn produce(
+                producer: &mut #commands::args::ArgsParserCtx<'a, E>,
+            ) -> #core::errors::Result<Self> {
+                producer.next_arg_with(#expected, |text| {
+                    match #commands::__macro_priv::normalize_enum_arg(text).as_str() {
+                        #(#match_names => #core::__macro_export::Some(#name::#variants),)*
+                        _ => #core::__macro_export::None,
+                    }
+                })
+            }
+        }
+    }).into())
+}
+
+#[derive(FromMeta, Debug, Default)]
+struct FlagAttrs {
+    #[darling(default)]
+    name: Option<String>,
+    #[darling(default)]
+    short: Option<String>,
+}
+impl FlagAttrs {
+    fn from_attrs(attrs: &[Attribute]) -> Result<FlagAttrs> {
+        let mut flag_attrs = None;
+        for attr in attrs {
+            if last_path_segment(&attr.path) == "flag" {
+                if flag_attrs.is_some() {
+                    error(attr.span(), "#[flag] can only be used once.")?;
+                }
+                flag_attrs = Some(FromMeta::from_meta(&attr.parse_meta()?)?);
+            }
+        }
+        Ok(flag_attrs.unwrap_or_default())
+    }
+}
+
+pub(crate) fn derive_parse_flags(paths: &CratePaths, input: TokenStream) -> Result<TokenStream> {
+    let input: DeriveInput = parse(input)?;
+    let core = &paths.core;
+    let commands = &paths.commands;
+    let static_events = quote! { #core::__macro_export::static_events::prelude_async };
+
+    if !input.generics.params.is_empty() {
+        error(input.generics.span(), "#[derive(ParseFlags)] does not support generic types.")?;
+    }
+    let fields = match &input.data {
+        Data::Struct(DataStruct { fields: Fields::Named(fields), .. }) => &fields.named,
+        _ => error(input.span(), "#[derive(ParseFlags)] may only be used with named structs.")?,
+    };
+
+    let name = &input.ident;
+    let mut field_names = Vec::new();
+    let mut field_values = Vec::new();
+    for field in fields {
+        let attrs = FlagAttrs::from_attrs(&field.attrs)?;
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+
+        let mut names = vec![match attrs.name {
+            Some(name) => name,
+            None => ident.to_string().trim_start_matches("r#").replace('_', "-"),
+        }];
+        if let Some(short) = attrs.short {
+            if short.chars().count() != 1 {
+                error(field.span(), "#[flag(short)] must be a single character.")?;
+            }
+            names.push(short);
+        }
+
+        field_names.push(ident);
+        field_values.push(quote! {
+            <#ty as #commands::args::ParseFlag<'a, E>>::produce(producer, &[#(#names,)*])?
+        });
+    }
+
+    Ok((quote! {
+        impl <'a, E: #static_events::Events> #commands::args::ParseArg<'a, E> for #name {
+            fn produce(
+                producer: &mut #commands::args::ArgsParserCtx<'a, E>,
+            ) -> #core::errors::Result<Self> {
+                #core::__macro_export::Ok(#name {
+                    #(#field_names: #field_values,)*
+                })
+            }
+        }
+    }).into())
+}
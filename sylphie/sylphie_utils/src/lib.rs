@@ -7,3 +7,4 @@ pub mod disambiguate;
 pub mod locks;
 pub mod scopes;
 pub mod strings;
+pub mod time;
@@ -2,6 +2,7 @@
 
 use crate::strings::StringWrapper;
 use serde::*;
+use std::fmt;
 
 /// The data contained in a scope.
 #[derive(Serialize, Deserialize, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
@@ -26,6 +27,20 @@ pub enum ScopeArgs {
     /// A scope containing three 32-bit integers.
     Int3(u32, u32, u32),
 }
+impl fmt::Display for ScopeArgs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScopeArgs::None => Ok(()),
+            ScopeArgs::String(s) => write!(f, "{:?}", s.as_str()),
+            ScopeArgs::Long(a) => write!(f, "{}", a),
+            ScopeArgs::Long2(a, b) => write!(f, "{}, {}", a, b),
+            ScopeArgs::Long3(a, b, c) => write!(f, "{}, {}, {}", a, b, c),
+            ScopeArgs::Int(a) => write!(f, "{}", a),
+            ScopeArgs::Int2(a, b) => write!(f, "{}, {}", a, b),
+            ScopeArgs::Int3(a, b, c) => write!(f, "{}, {}, {}", a, b, c),
+        }
+    }
+}
 
 /// A tagged scope used as an identifier.
 #[derive(Serialize, Deserialize, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
@@ -41,4 +56,12 @@ impl Scope {
             args,
         }
     }
-}
\ No newline at end of file
+}
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.args {
+            ScopeArgs::None => write!(f, "{}", self.scope_type),
+            _ => write!(f, "{}({})", self.scope_type, self.args),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Helpers for reading and displaying human-readable durations.
+
+use std::time::Duration;
+use sylphie_core::errors::*;
+
+const UNITS: &[(&str, u64)] = &[
+    ("w", 7 * 24 * 60 * 60),
+    ("d", 24 * 60 * 60),
+    ("h", 60 * 60),
+    ("m", 60),
+    ("s", 1),
+];
+
+/// Parses a duration such as `30s`, `10m` or `1h30m`.
+///
+/// The supported units are `w`, `d`, `h`, `m` and `s`. A plain number with no units is parsed
+/// as a number of seconds.
+pub fn parse_duration(source: &str) -> Result<Duration> {
+    let source = source.trim();
+    if source.is_empty() {
+        cmd_error!("Durations cannot be empty.");
+    }
+    if source.bytes().all(|x| x.is_ascii_digit()) {
+        let secs = source.parse::<u64>()
+            .cmd_error(|| format!("Duration '{}' is too long.", source))?;
+        return Ok(Duration::from_secs(secs))
+    }
+
+    let mut total = 0u64;
+    let mut rest = source;
+    while !rest.is_empty() {
+        let digits_len = rest.find(|x: char| !x.is_ascii_digit()).unwrap_or(rest.len());
+        if digits_len == 0 {
+            cmd_error!("Could not parse duration '{}'.", source);
+        }
+        let count = rest[..digits_len].parse::<u64>()
+            .cmd_error(|| format!("Duration '{}' is too long.", source))?;
+        rest = &rest[digits_len..];
+
+        let unit_len = rest.find(|x: char| x.is_ascii_digit()).unwrap_or(rest.len());
+        let unit = rest[..unit_len].to_ascii_lowercase();
+        rest = &rest[unit_len..];
+        let multiplier = match UNITS.iter().find(|(name, _)| *name == unit) {
+            Some((_, multiplier)) => *multiplier,
+            None => cmd_error!("Unknown unit '{}' in duration '{}'.", unit, source),
+        };
+
+        total = count.checked_mul(multiplier)
+            .and_then(|x| x.checked_add(total))
+            .cmd_error(|| format!("Duration '{}' is too long.", source))?;
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Formats a duration in the format accepted by [`parse_duration`], rounded down to the nearest
+/// second.
+pub fn format_duration(duration: Duration) -> String {
+    let mut secs = duration.as_secs();
+    if secs == 0 {
+        return "0s".to_string()
+    }
+
+    let mut out = String::new();
+    for (name, multiplier) in UNITS {
+        if secs >= *multiplier {
+            out.push_str(&format!("{}{}", secs / multiplier, name));
+            secs %= multiplier;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_parse(source: &str, secs: u64) {
+        assert_eq!(parse_duration(source).unwrap(), Duration::from_secs(secs));
+    }
+
+    #[test]
+    fn parse_test() {
+        check_parse("30", 30);
+        check_parse("30s", 30);
+        check_parse("10m", 600);
+        check_parse("1h30m", 5400);
+        check_parse("1H30M", 5400);
+        check_parse("2w1d", 15 * 24 * 60 * 60);
+        check_parse(" 5m ", 300);
+        check_parse("1m1m", 120);
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("10 m").is_err());
+        assert!(parse_duration("1.5h").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+        assert!(parse_duration("9999999999999999w").is_err());
+    }
+
+    #[test]
+    fn format_test() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1s");
+        assert_eq!(format_duration(Duration::from_secs(5400)), "1h30m");
+        assert_eq!(format_duration(Duration::from_secs(694861)), "1w1d1h1m1s");
+        for secs in &[1, 59, 60, 3599, 86400, 1000000] {
+            let duration = Duration::from_secs(*secs);
+            assert_eq!(parse_duration(&format_duration(duration)).unwrap(), duration);
+        }
+    }
+}
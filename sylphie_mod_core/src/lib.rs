@@ -1,21 +1,28 @@
 use sylphie::commands::manager::CommandManager;
+use sylphie::commands::module_state::ModuleStateManager;
+use sylphie::commands::permissions::{PermissionManager, PermissionPolicy};
 use sylphie::database::config::*;
+use sylphie::module::ModuleManager;
 use sylphie::prelude::*;
 use sylphie::utils::disambiguate::LookupResult;
 
+/// Returns the most specific scope a command was used in.
+fn current_scope(ctx: &CommandCtx<impl Events>) -> Result<Scope> {
+    match ctx.scopes().first() {
+        Some(scope) => Ok(scope.clone()),
+        None => cmd_error!("This command cannot be used here."),
+    }
+}
+
 /// A module that can be added to a Sylphie bot to add core bot commands.
 #[derive(Module)]
+#[module(requires(CommandManager, ConfigManager, ModuleStateManager, PermissionManager))]
 pub struct ModCore {
     #[module_info] info: ModuleInfo,
 }
 
 #[module_impl]
 impl ModCore {
-    #[config]
-    pub const CFG_PREFIX: ConfigKey<String> = config_option!(
-        Any, "prefix f1733370-515d-43f8-87b4-8b2833cfdd9d", || "!".to_string(),
-    );
-
     #[command]
     async fn cmd_help(
         &self, ctx: &CommandCtx<impl Events>, target_cmd: Option<String>,
@@ -52,22 +59,141 @@ impl ModCore {
         Ok(())
     }
 
-    #[command]
+    #[command(permission = "admin")]
     async fn cmd_shutdown(&self, ctx: &CommandCtx<impl Events>) -> Result<()> {
         ctx.handler().shutdown_bot();
         Ok(())
     }
 
-    #[command]
+    #[command(permission = "admin")]
     async fn cmd_show_config(&self, ctx: &CommandCtx<impl Events>) -> Result<()> {
+        let scope = current_scope(ctx)?;
         ctx.respond("Configuration options:").await?;
         for cfg in &*ctx.handler().get_service::<ConfigManager>().option_list() {
             ctx.respond(&format!(
                 "* {}: {}",
                 cfg.shortest_name,
-                cfg.value.get_display(ctx.handler(), ctx.scopes()[0].clone()).await?,
+                cfg.value.get_display(ctx.handler(), scope.clone()).await?,
+            )).await?;
+        }
+        Ok(())
+    }
+
+    #[command]
+    async fn cmd_modules(&self, ctx: &CommandCtx<impl Events>) -> Result<()> {
+        let state = ctx.handler().get_service::<ModuleStateManager>();
+        ctx.respond("Loaded modules:").await?;
+        for module in ctx.handler().get_service::<ModuleManager>().loaded_modules() {
+            let status = if state.is_integral(ctx.handler(), module) {
+                "integral"
+            } else if state.is_enabled(ctx.handler(), module, ctx.scopes()) {
+                "enabled"
+            } else {
+                "disabled"
+            };
+            ctx.respond(&format!("* {} ({})", module.name(), status)).await?;
+        }
+        Ok(())
+    }
+
+    #[command(permission = "admin")]
+    async fn cmd_enable(&self, ctx: &CommandCtx<impl Events>, module: String) -> Result<()> {
+        self.set_module_enabled(ctx, &module, true).await
+    }
+
+    #[command(permission = "admin")]
+    async fn cmd_disable(&self, ctx: &CommandCtx<impl Events>, module: String) -> Result<()> {
+        self.set_module_enabled(ctx, &module, false).await
+    }
+
+    async fn set_module_enabled(
+        &self, ctx: &CommandCtx<impl Events>, module: &str, enabled: bool,
+    ) -> Result<()> {
+        let info = match ctx.handler().get_service::<ModuleManager>().find_module(module) {
+            Some(info) => info,
+            None => cmd_error!("No such module '{}' exists!", module),
+        };
+        let scope = current_scope(ctx)?;
+        ctx.handler().get_service::<ModuleStateManager>()
+            .set_enabled(ctx.handler(), info, scope.clone(), enabled).await?;
+        ctx.respond(&format!(
+            "Module '{}' is now {} in {}.",
+            info.name(), if enabled { "enabled" } else { "disabled" }, scope,
+        )).await?;
+        Ok(())
+    }
+
+    #[command(permission = "admin")]
+    async fn cmd_grant(
+        &self, ctx: &CommandCtx<impl Events>, permission: String, scope: Option<Scope>,
+    ) -> Result<()> {
+        self.set_permission(ctx, &permission, scope, true).await
+    }
+
+    #[command(permission = "admin")]
+    async fn cmd_deny(
+        &self, ctx: &CommandCtx<impl Events>, permission: String, scope: Option<Scope>,
+    ) -> Result<()> {
+        self.set_permission(ctx, &permission, scope, false).await
+    }
+
+    #[command(permission = "admin")]
+    async fn cmd_revoke(
+        &self, ctx: &CommandCtx<impl Events>, permission: String, scope: Option<Scope>,
+    ) -> Result<()> {
+        let scope = match scope {
+            Some(scope) => scope,
+            None => current_scope(ctx)?,
+        };
+        let permissions = ctx.handler().get_service::<PermissionManager>();
+        if permissions.reset_permission(&permission, &scope).await? {
+            ctx.respond(&format!(
+                "Permission '{}' is no longer explicitly set in {}.", permission, scope,
             )).await?;
+        } else {
+            cmd_error!("Permission '{}' was not explicitly set in {}.", permission, scope);
         }
         Ok(())
     }
+
+    #[command(permission = "admin")]
+    async fn cmd_permissions(&self, ctx: &CommandCtx<impl Events>) -> Result<()> {
+        let permissions = ctx.handler().get_service::<PermissionManager>();
+        ctx.respond("Permissions:").await?;
+        for permission in permissions.known_permissions(ctx.handler()) {
+            let granted = ctx.is_operator() ||
+                permissions.has_permission(&permission, ctx.scopes()).await?;
+            let policy = match permissions.policy(&permission) {
+                PermissionPolicy::DefaultAllow => "allowed by default",
+                PermissionPolicy::DefaultDeny => "denied by default",
+            };
+            ctx.respond(&format!(
+                "* {}: {} here, {}",
+                permission, if granted { "granted" } else { "denied" }, policy,
+            )).await?;
+            for (scope, granted) in permissions.grants(&permission).await? {
+                ctx.respond(&format!(
+                    "    {} in {}", if granted { "granted" } else { "denied" }, scope,
+                )).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_permission(
+        &self, ctx: &CommandCtx<impl Events>, permission: &str, scope: Option<Scope>,
+        granted: bool,
+    ) -> Result<()> {
+        let scope = match scope {
+            Some(scope) => scope,
+            None => current_scope(ctx)?,
+        };
+        ctx.handler().get_service::<PermissionManager>()
+            .set_permission(permission, scope.clone(), granted).await?;
+        ctx.respond(&format!(
+            "Permission '{}' is now {} in {}.",
+            permission, if granted { "granted" } else { "denied" }, scope,
+        )).await?;
+        Ok(())
+    }
 }
@@ -58,8 +58,9 @@ impl MyModule {
 
     #[command]
     async fn cmd_kvs_set(
-        &self, ctx: &CommandCtx<impl Events>, key: String, val: String,
+        &self, ctx: &CommandCtx<impl Events>, key: String, val: Rest,
     ) -> Result<()> {
+        let val = val.0;
         let cur = self.kvs.get(key.clone()).await?;
         ctx.respond(&format!("Current value for {}: {:?}", key, cur)).await?;
         self.kvs.set(key.clone(), val.clone()).await?;
@@ -88,5 +89,5 @@ sylphie_root_module! {
 }
 
 fn main() {
-    SylphieCore::<Test>::new("test_bot").start().unwrap();
+    sylphie::cli::main(SylphieCore::<Test>::builder("test_bot"));
 }